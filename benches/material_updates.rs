@@ -0,0 +1,173 @@
+//! Frame times of 10k outlines whose materials are modified every frame, once with the shared
+//! material uniform buffer and once with a buffer and bind group recreated per modified material.
+//!
+//! Run with `cargo bench --bench material_updates`. It renders into an image, so it needs a GPU
+//! adapter but no window. Each frame waits for the GPU, so the frame time covers its work too.
+
+use std::time::{Duration, Instant};
+
+use bevy::{
+    core_pipeline::core_3d::Camera3dBundle,
+    prelude::*,
+    render::{
+        camera::RenderTarget,
+        render_resource::{
+            Extent3d, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
+        },
+        renderer::RenderDevice,
+        texture::BevyDefault,
+        RenderApp,
+    },
+    winit::WinitPlugin,
+};
+use bevy_outline::{OutlineMaterial, OutlinePlugin, OutlineSettings};
+
+/// Number of cubes along one side of the grid, i.e. 10k outlined cubes
+const GRID_SIZE: i32 = 100;
+/// Frames rendered before measuring, e.g. while the pipelines compile
+const WARMUP_FRAMES: usize = 120;
+const MEASURED_FRAMES: usize = 600;
+
+fn main() {
+    let mut app = App::new();
+    app.insert_resource(Msaa { samples: 4 })
+        .add_plugins(
+            DefaultPlugins
+                .set(WindowPlugin {
+                    add_primary_window: false,
+                    exit_on_all_closed: false,
+                    ..default()
+                })
+                .build()
+                .disable::<WinitPlugin>(),
+        )
+        .add_plugin(OutlinePlugin::default())
+        .add_startup_system(setup)
+        .add_system(animate_outlines);
+
+    println!(
+        "{} outlined cubes with animated materials, {} frames per path",
+        GRID_SIZE * GRID_SIZE,
+        MEASURED_FRAMES
+    );
+    for (name, shared) in [
+        ("shared uniform buffer", true),
+        ("recreated per material", false),
+    ] {
+        let frame_times = measure(&mut app, shared);
+        report(name, frame_times);
+    }
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut outlines: ResMut<Assets<OutlineMaterial>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let mesh = meshes.add(Mesh::from(shape::Cube { size: 0.5 }));
+    let material = materials.add(Color::rgb(0.8, 0.7, 0.6).into());
+
+    // Every cube has its own outline material, which is modified every frame
+    for x in 0..GRID_SIZE {
+        for z in 0..GRID_SIZE {
+            commands
+                .spawn_bundle(PbrBundle {
+                    mesh: mesh.clone(),
+                    material: material.clone(),
+                    transform: Transform::from_xyz(
+                        (x - GRID_SIZE / 2) as f32,
+                        0.,
+                        (z - GRID_SIZE / 2) as f32,
+                    ),
+                    ..default()
+                })
+                .insert(outlines.add(OutlineMaterial {
+                    width: 2.,
+                    ..default()
+                }));
+        }
+    }
+
+    commands.spawn_bundle(DirectionalLightBundle {
+        transform: Transform::from_xyz(1., 2., 1.).looking_at(Vec3::ZERO, Vec3::Y),
+        ..default()
+    });
+
+    let size = Extent3d {
+        width: 1920,
+        height: 1080,
+        ..default()
+    };
+    let mut image = Image {
+        texture_descriptor: TextureDescriptor {
+            label: None,
+            size,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::bevy_default(),
+            mip_level_count: 1,
+            sample_count: 1,
+            usage: TextureUsages::TEXTURE_BINDING
+                | TextureUsages::COPY_SRC
+                | TextureUsages::RENDER_ATTACHMENT,
+        },
+        ..default()
+    };
+    image.resize(size);
+
+    commands.spawn_bundle(Camera3dBundle {
+        camera: Camera {
+            target: RenderTarget::Image(images.add(image)),
+            ..default()
+        },
+        transform: Transform::from_xyz(0., 40., 60.).looking_at(Vec3::ZERO, Vec3::Y),
+        ..default()
+    });
+}
+
+fn animate_outlines(mut frame: Local<u32>, mut outlines: ResMut<Assets<OutlineMaterial>>) {
+    *frame += 1;
+    for (index, (_, outline)) in outlines.iter_mut().enumerate() {
+        let hue = (*frame as f32 * 1.5 + index as f32 * 0.1) % 360.;
+        outline.color = Color::hsl(hue, 1., 0.5);
+    }
+}
+
+fn measure(app: &mut App, shared: bool) -> Vec<Duration> {
+    app.world
+        .resource_mut::<OutlineSettings>()
+        .shared_material_uniforms = shared;
+    // Every material is modified every frame, so all of them switch paths during the warmup
+    for _ in 0..WARMUP_FRAMES {
+        render_frame(app);
+    }
+    (0..MEASURED_FRAMES)
+        .map(|_| {
+            let start = Instant::now();
+            render_frame(app);
+            start.elapsed()
+        })
+        .collect()
+}
+
+fn render_frame(app: &mut App) {
+    app.update();
+    app.sub_app(RenderApp)
+        .world
+        .resource::<RenderDevice>()
+        .poll(wgpu_types::Maintain::Wait);
+}
+
+fn report(name: &str, mut frame_times: Vec<Duration>) {
+    frame_times.sort();
+    let mean = frame_times.iter().sum::<Duration>() / frame_times.len() as u32;
+    let median = frame_times[frame_times.len() / 2];
+    let p95 = frame_times[frame_times.len() * 95 / 100];
+    println!(
+        "{name}: mean {:.2} ms, median {:.2} ms, p95 {:.2} ms",
+        mean.as_secs_f64() * 1000.,
+        median.as_secs_f64() * 1000.,
+        p95.as_secs_f64() * 1000.,
+    );
+}
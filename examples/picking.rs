@@ -2,7 +2,9 @@ use bevy::prelude::*;
 use bevy_mod_picking::{PickableBundle, PickingCameraBundle};
 // use bevy_obj::ObjPlugin;
 use bevy_outline::{
-    picking::{DefaultPickingPlugins, HoverOutline, PressedOutline, SelectedOutline},
+    picking::{
+        DefaultPickingPlugins, HoverOutline, PickingOutlines, PressedOutline, SelectedOutline,
+    },
     OutlineMaterial,
 };
 
@@ -22,15 +24,18 @@ fn set_picking_outlines(mut commands: Commands, mut outlines: ResMut<Assets<Outl
     commands.insert_resource(HoverOutline(outlines.add(OutlineMaterial {
         width: 5.,
         color: Color::BLACK,
+        ..default()
     })));
     commands.insert_resource(SelectedOutline(outlines.add(OutlineMaterial {
         width: 5.,
         color: Color::WHITE,
+        ..default()
     })));
     // Uncomment below to enable pressed outline
     // commands.insert_resource(SelectedOutline(OutlineMaterial {
     //     width: 5.,
     //     color: Color::WHITE,
+    //     ..default()
     // }));
 }
 
@@ -40,6 +45,7 @@ fn setup(
     mut ambient_light: ResMut<AmbientLight>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
+    mut outlines: ResMut<Assets<OutlineMaterial>>,
 ) {
     // Cube
     commands
@@ -59,7 +65,16 @@ fn setup(
             transform: Transform::from_xyz(-2.0, 0.5, 0.0),
             ..default()
         })
-        .insert_bundle(PickableBundle::default());
+        .insert_bundle(PickableBundle::default())
+        // The sphere is outlined in red when hovered
+        .insert(PickingOutlines {
+            hover: Some(outlines.add(OutlineMaterial {
+                width: 5.,
+                color: Color::RED,
+                ..default()
+            })),
+            ..default()
+        });
 
     // Torus
     // The built-in torus has some mistakes which will be fixed in 0.8
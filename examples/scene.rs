@@ -4,7 +4,7 @@ use bevy::{
     prelude::*,
 };
 // use bevy_obj::ObjPlugin;
-use bevy_outline::{OutlineMaterial, OutlinePlugin};
+use bevy_outline::{OutlineDash, OutlineLayer, OutlineMaterial, OutlinePlugin, Outlines};
 
 fn main() {
     println!(
@@ -15,10 +15,14 @@ fn main() {
     );
     App::new()
         .insert_resource(Msaa { samples: 4 })
-        .add_plugins(DefaultPlugins)
+        // Reload outline styles in `assets/styles` when they are edited
+        .add_plugins(DefaultPlugins.set(AssetPlugin {
+            watch_for_changes: true,
+            ..default()
+        }))
         // bevy_obj does not track the main branch
         // .add_plugin(ObjPlugin)
-        .add_plugin(OutlinePlugin)
+        .add_plugin(OutlinePlugin::default())
         .add_system(pan_orbit_camera)
         .add_startup_system(setup)
         .run();
@@ -26,7 +30,7 @@ fn main() {
 
 fn setup(
     mut commands: Commands,
-    // asset_server: Res<AssetServer>,
+    asset_server: Res<AssetServer>,
     mut ambient_light: ResMut<AmbientLight>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
@@ -35,11 +39,20 @@ fn setup(
     let outline_black = outlines.add(OutlineMaterial {
         width: 5.,
         color: Color::rgba(0.0, 0.0, 0.0, 1.0),
+        ..default()
     });
 
     let outline_white = outlines.add(OutlineMaterial {
         width: 3.,
         color: Color::rgba(1.0, 1.0, 1.0, 1.0),
+        ..default()
+    });
+
+    let outline_dashed = outlines.add(OutlineMaterial {
+        width: 3.,
+        color: Color::BLACK,
+        dash: Some(OutlineDash::marching_ants(8., Color::WHITE)),
+        ..default()
     });
 
     // Cube
@@ -60,7 +73,14 @@ fn setup(
             transform: Transform::from_xyz(-2.0, 0.5, 0.0),
             ..default()
         })
-        .insert(outline_white.clone());
+        .insert(Outlines(vec![
+            OutlineLayer::new(outlines.add(OutlineMaterial {
+                width: 8.,
+                color: Color::BLACK,
+                ..default()
+            })),
+            OutlineLayer::new(outline_white.clone()),
+        ]));
 
     // Torus
     commands
@@ -70,7 +90,17 @@ fn setup(
             transform: Transform::from_xyz(6.0, 0.5, 0.0),
             ..default()
         })
-        .insert(outline_white.clone());
+        .insert(outline_dashed);
+
+    // Capsule with an outline style loaded from a file
+    commands
+        .spawn_bundle(PbrBundle {
+            mesh: meshes.add(Mesh::from(shape::Capsule::default())),
+            material: materials.add(Color::rgb(0.6, 0.6, 0.6).into()),
+            transform: Transform::from_xyz(0.0, 1.0, -3.0),
+            ..default()
+        })
+        .insert(asset_server.load::<OutlineMaterial, _>("styles/selected.outline.ron"));
 
     // Monkey head
     // commands
@@ -0,0 +1,101 @@
+use bevy::{
+    core_pipeline::core_3d::Camera3dBundle,
+    diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin},
+    prelude::*,
+};
+use bevy_outline::{OutlineDiagnosticsPlugin, OutlineMaterial, OutlinePlugin, OutlineSettings};
+
+/// Number of cubes along one side of the grid, i.e. 10k outlined cubes
+const GRID_SIZE: i32 = 100;
+
+fn main() {
+    // Start with a buffer and bind group per material to compare it with the shared buffer
+    let recreate = std::env::args().any(|arg| arg == "--recreate");
+    println!(
+        "Animating the outline colors of {} cubes. Frame times and outline diagnostics are logged every second.",
+        GRID_SIZE * GRID_SIZE
+    );
+    println!("Press space to switch between the shared uniform buffer and recreating materials.");
+    App::new()
+        .insert_resource(Msaa { samples: 4 })
+        .add_plugins(DefaultPlugins)
+        .add_plugin(FrameTimeDiagnosticsPlugin::default())
+        .add_plugin(LogDiagnosticsPlugin::default())
+        .add_plugin(OutlinePlugin::default())
+        .add_plugin(OutlineDiagnosticsPlugin)
+        .insert_resource(OutlineSettings {
+            shared_material_uniforms: !recreate,
+            ..default()
+        })
+        .add_startup_system(setup)
+        .add_system(animate_outlines)
+        .add_system(toggle_update_mode)
+        .add_system(log_update_mode.after(toggle_update_mode))
+        .run();
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut outlines: ResMut<Assets<OutlineMaterial>>,
+) {
+    let mesh = meshes.add(Mesh::from(shape::Cube { size: 0.5 }));
+    let material = materials.add(Color::rgb(0.8, 0.7, 0.6).into());
+
+    // Every cube has its own outline material, which is modified every frame
+    for x in 0..GRID_SIZE {
+        for z in 0..GRID_SIZE {
+            commands
+                .spawn_bundle(PbrBundle {
+                    mesh: mesh.clone(),
+                    material: material.clone(),
+                    transform: Transform::from_xyz(
+                        (x - GRID_SIZE / 2) as f32,
+                        0.,
+                        (z - GRID_SIZE / 2) as f32,
+                    ),
+                    ..default()
+                })
+                .insert(outlines.add(OutlineMaterial {
+                    width: 2.,
+                    ..default()
+                }));
+        }
+    }
+
+    commands.spawn_bundle(DirectionalLightBundle {
+        transform: Transform::from_xyz(1., 2., 1.).looking_at(Vec3::ZERO, Vec3::Y),
+        ..default()
+    });
+
+    commands.spawn_bundle(Camera3dBundle {
+        transform: Transform::from_xyz(0., 40., 60.).looking_at(Vec3::ZERO, Vec3::Y),
+        ..default()
+    });
+}
+
+fn animate_outlines(time: Res<Time>, mut outlines: ResMut<Assets<OutlineMaterial>>) {
+    let t = time.elapsed_seconds();
+    for (index, (_, outline)) in outlines.iter_mut().enumerate() {
+        let hue = (t * 90. + index as f32 * 0.1) % 360.;
+        outline.color = Color::hsl(hue, 1., 0.5);
+    }
+}
+
+fn toggle_update_mode(keys: Res<Input<KeyCode>>, mut settings: ResMut<OutlineSettings>) {
+    if keys.just_pressed(KeyCode::Space) {
+        settings.shared_material_uniforms = !settings.shared_material_uniforms;
+    }
+}
+
+fn log_update_mode(settings: Res<OutlineSettings>) {
+    if !settings.is_changed() {
+        return;
+    }
+    if settings.shared_material_uniforms {
+        info!("Modified outline materials are written into the shared uniform buffer");
+    } else {
+        info!("Modified outline materials recreate their buffers and bind groups");
+    }
+}
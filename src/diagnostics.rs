@@ -0,0 +1,126 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc, Mutex,
+};
+
+use bevy::{
+    diagnostic::{Diagnostic, DiagnosticId, Diagnostics},
+    prelude::{App, CoreStage, Deref, Entity, Plugin, Res, ResMut, Resource},
+    render::{render_resource::CachedRenderPipelineId, RenderApp, RenderStage},
+    utils::{Duration, HashMap, HashSet},
+};
+
+/// Adds outline render diagnostics to an App.
+///
+/// Render statistics are those of the previous frame, as the render world runs after the main world.
+#[derive(Default)]
+pub struct OutlineDiagnosticsPlugin;
+
+impl Plugin for OutlineDiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        let stats = OutlineStats::default();
+        app.insert_resource(stats.clone())
+            .init_resource::<OutlineViewDiagnostics>()
+            .add_startup_system(Self::setup_system)
+            .add_system_to_stage(CoreStage::Last, Self::diagnostic_system);
+
+        if let Ok(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app
+                .insert_resource(stats)
+                .add_system_to_stage(RenderStage::Prepare, reset_outline_stats);
+        }
+    }
+}
+
+impl OutlineDiagnosticsPlugin {
+    /// Outlined entities queued in all views
+    pub const QUEUED_OUTLINES: DiagnosticId =
+        DiagnosticId::from_u128(271406345812398734526194718330751302721);
+    /// Outline draw calls, instanced outlines are drawn by a single call
+    pub const DRAW_CALLS: DiagnosticId =
+        DiagnosticId::from_u128(112079405365928309150741290575812893413);
+    /// Distinct outline pipelines specialized since startup
+    pub const PIPELINES: DiagnosticId =
+        DiagnosticId::from_u128(197237841295806151263946218937406523150);
+    /// Time spent in `prepare_outline_mesh`
+    pub const PREPARE_MESH_TIME: DiagnosticId =
+        DiagnosticId::from_u128(62390823107417836289571340212653720934);
+
+    pub fn setup_system(mut diagnostics: ResMut<Diagnostics>) {
+        diagnostics.add(Diagnostic::new(
+            Self::QUEUED_OUTLINES,
+            "queued_outlines",
+            20,
+        ));
+        diagnostics.add(Diagnostic::new(Self::DRAW_CALLS, "outline_draw_calls", 20));
+        diagnostics.add(Diagnostic::new(Self::PIPELINES, "outline_pipelines", 20));
+        diagnostics.add(
+            Diagnostic::new(Self::PREPARE_MESH_TIME, "outline_prepare_mesh_time", 20)
+                .with_suffix("ms"),
+        );
+    }
+
+    fn diagnostic_system(
+        mut diagnostics: ResMut<Diagnostics>,
+        mut views: ResMut<OutlineViewDiagnostics>,
+        stats: Res<OutlineStats>,
+    ) {
+        views.0 = stats.0.queued.lock().unwrap().clone();
+        diagnostics.add_measurement(Self::QUEUED_OUTLINES, || {
+            views.values().sum::<usize>() as f64
+        });
+        diagnostics.add_measurement(Self::DRAW_CALLS, || {
+            stats.0.draw_calls.load(Ordering::Relaxed) as f64
+        });
+        diagnostics.add_measurement(Self::PIPELINES, || {
+            stats.0.pipelines.lock().unwrap().len() as f64
+        });
+        let prepare_mesh_time = std::mem::take(&mut *stats.0.prepare_mesh_time.lock().unwrap());
+        diagnostics.add_measurement(Self::PREPARE_MESH_TIME, || {
+            prepare_mesh_time.as_secs_f64() * 1000.
+        });
+    }
+}
+
+/// Outlined entities queued in the previous frame, keyed by camera entity
+#[derive(Resource, Default, Debug, Clone, Deref)]
+pub struct OutlineViewDiagnostics(pub HashMap<Entity, usize>);
+
+/// Outline statistics shared by the main world and the render world.
+///
+/// It only exists if [`OutlineDiagnosticsPlugin`] is added, read it through [`Diagnostics`].
+#[derive(Resource, Clone, Default)]
+pub struct OutlineStats(Arc<OutlineStatsInner>);
+
+#[derive(Default)]
+struct OutlineStatsInner {
+    queued: Mutex<HashMap<Entity, usize>>,
+    pipelines: Mutex<HashSet<CachedRenderPipelineId>>,
+    draw_calls: AtomicUsize,
+    prepare_mesh_time: Mutex<Duration>,
+}
+
+impl OutlineStats {
+    pub(crate) fn record_queued(
+        &self,
+        view: Entity,
+        queued: usize,
+        pipelines: impl IntoIterator<Item = CachedRenderPipelineId>,
+    ) {
+        *self.0.queued.lock().unwrap().entry(view).or_default() += queued;
+        self.0.pipelines.lock().unwrap().extend(pipelines);
+    }
+
+    pub(crate) fn record_draw_call(&self) {
+        self.0.draw_calls.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_prepare_mesh_time(&self, time: Duration) {
+        *self.0.prepare_mesh_time.lock().unwrap() += time;
+    }
+}
+
+fn reset_outline_stats(stats: Res<OutlineStats>) {
+    stats.0.queued.lock().unwrap().clear();
+    stats.0.draw_calls.store(0, Ordering::Relaxed);
+}
@@ -0,0 +1,142 @@
+use bevy::{
+    asset::HandleId,
+    prelude::{Changed, Entity, EventWriter, Handle, Local, Query, RemovedComponents},
+    utils::{HashMap, HashSet},
+};
+
+use crate::{highlight::PickingHighlighted, OutlineMaterial, Outlines};
+
+/// Sent when an entity gets a `Handle<OutlineMaterial>`, or its [`Outlines`] get their first layer.
+///
+/// Entities outlined by custom materials send no outline events,
+/// query `Changed<Handle<M>>` instead.
+#[derive(Debug, Clone)]
+pub struct OutlineAdded {
+    pub entity: Entity,
+    /// The outline material, or that of the first layer for [`Outlines`]
+    pub outline: Handle<OutlineMaterial>,
+    /// Whether the outline is a picking highlight
+    pub highlighted: bool,
+    /// Whether the outline comes from [`Outlines`] layers
+    pub layers: bool,
+}
+
+/// Sent when an entity loses its `Handle<OutlineMaterial>`, its [`Outlines`] lose their last
+/// layer, or the entity is despawned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutlineRemoved {
+    pub entity: Entity,
+    /// Whether the outline came from [`Outlines`] layers
+    pub layers: bool,
+}
+
+/// Sent when an entity gets another outline material, e.g. when it is hovered.
+///
+/// Changes of the `OutlineMaterial` assets are reported by their `AssetEvent`s instead.
+/// Changes of the layers of [`Outlines`] are not reported.
+#[derive(Debug, Clone)]
+pub struct OutlineStyleChanged {
+    pub entity: Entity,
+    /// Weak handle of the previous outline
+    pub previous: Handle<OutlineMaterial>,
+    pub outline: Handle<OutlineMaterial>,
+    /// Whether the outline is a picking highlight
+    pub highlighted: bool,
+}
+
+/// Report outline changes, including those of picking highlights.
+///
+/// Runs in `CoreStage::Last` to see the removals made by commands of `CoreStage::PostUpdate`.
+pub(crate) fn send_outline_events(
+    mut outlines: Local<HashMap<Entity, HandleId>>,
+    changed: Query<
+        (
+            Entity,
+            &Handle<OutlineMaterial>,
+            Option<&PickingHighlighted>,
+        ),
+        Changed<Handle<OutlineMaterial>>,
+    >,
+    current: Query<&Handle<OutlineMaterial>>,
+    removed: RemovedComponents<Handle<OutlineMaterial>>,
+    mut added_events: EventWriter<OutlineAdded>,
+    mut removed_events: EventWriter<OutlineRemoved>,
+    mut changed_events: EventWriter<OutlineStyleChanged>,
+) {
+    for (entity, outline, highlighted) in changed.iter() {
+        let highlighted = highlighted.is_some();
+        match outlines.insert(entity, outline.id()) {
+            None => added_events.send(OutlineAdded {
+                entity,
+                outline: outline.clone(),
+                highlighted,
+                layers: false,
+            }),
+            // Re-inserting the same handle is no change
+            Some(previous) if previous == outline.id() => {}
+            Some(previous) => changed_events.send(OutlineStyleChanged {
+                entity,
+                previous: Handle::weak(previous),
+                outline: outline.clone(),
+                highlighted,
+            }),
+        }
+    }
+    for entity in removed.iter() {
+        // The handle may have been removed and inserted again in the same frame
+        if current.contains(entity) {
+            continue;
+        }
+        if outlines.remove(&entity).is_some() {
+            removed_events.send(OutlineRemoved {
+                entity,
+                layers: false,
+            });
+        }
+    }
+}
+
+/// Report [`Outlines`] getting their first layer or losing their last one
+pub(crate) fn send_layer_events(
+    mut layered: Local<HashSet<Entity>>,
+    changed: Query<(Entity, &Outlines), Changed<Outlines>>,
+    current: Query<&Outlines>,
+    removed: RemovedComponents<Outlines>,
+    mut added_events: EventWriter<OutlineAdded>,
+    mut removed_events: EventWriter<OutlineRemoved>,
+) {
+    for (entity, outlines) in changed.iter() {
+        match outlines.first() {
+            Some(layer) => {
+                if layered.insert(entity) {
+                    added_events.send(OutlineAdded {
+                        entity,
+                        outline: layer.material.clone(),
+                        highlighted: false,
+                        layers: true,
+                    });
+                }
+            }
+            None => {
+                if layered.remove(&entity) {
+                    removed_events.send(OutlineRemoved {
+                        entity,
+                        layers: true,
+                    });
+                }
+            }
+        }
+    }
+    for entity in removed.iter() {
+        // The layers may have been removed and inserted again in the same frame
+        if current.contains(entity) {
+            continue;
+        }
+        if layered.remove(&entity) {
+            removed_events.send(OutlineRemoved {
+                entity,
+                layers: true,
+            });
+        }
+    }
+}
@@ -0,0 +1,221 @@
+//! Focus navigation with keyboard or gamepad, for players without a cursor.
+
+use bevy::{
+    input::InputSystem,
+    math::Vec2,
+    prelude::{
+        App, Camera, Commands, Component, CoreStage, Entity, EventReader, EventWriter,
+        GamepadButton, GamepadButtonType, Gamepads, GlobalTransform, Input, IntoSystemDescriptor,
+        KeyCode, Plugin, Query, Res, SystemLabel, With,
+    },
+};
+
+use crate::highlight::{active_camera, add_highlighting, PickingFocused};
+
+/// Moves the [`PickingFocused`] marker between [`Focusable`] entities with the arrow keys
+/// and the gamepad D-pad, to the nearest entity on screen in the pressed direction.
+///
+/// The focused entity is outlined with the [`PickingState::Focused`](crate::highlight::PickingState::Focused)
+/// outline of [`PickingOutlineStates`](crate::highlight::PickingOutlineStates).
+pub struct OutlineFocusPlugin;
+
+impl Plugin for OutlineFocusPlugin {
+    fn build(&self, app: &mut App) {
+        add_highlighting(app);
+        app.add_event::<FocusMove>()
+            .add_event::<FocusChanged>()
+            .add_system_to_stage(
+                CoreStage::PreUpdate,
+                focus_input.label(FocusSystem::Input).after(InputSystem),
+            )
+            .add_system_to_stage(
+                CoreStage::PreUpdate,
+                navigate_focus
+                    .label(FocusSystem::Navigation)
+                    .after(FocusSystem::Input),
+            );
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, SystemLabel)]
+pub enum FocusSystem {
+    /// Sends [`FocusMove`] events from keyboard and gamepad input
+    Input,
+    /// Moves the focus
+    Navigation,
+}
+
+/// Marks entities which can receive the focus
+#[derive(Component, Default)]
+pub struct Focusable;
+
+/// Direction on screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FocusDirection {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl FocusDirection {
+    /// Unit vector in viewport coordinates, whose y axis points up
+    fn vector(self) -> Vec2 {
+        match self {
+            FocusDirection::Up => Vec2::Y,
+            FocusDirection::Down => Vec2::NEG_Y,
+            FocusDirection::Left => Vec2::NEG_X,
+            FocusDirection::Right => Vec2::X,
+        }
+    }
+}
+
+/// Request to move the focus, sent by the plugin or by the app for other input devices
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FocusMove(pub FocusDirection);
+
+/// Sent when the focus moved to another entity
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FocusChanged {
+    pub previous: Option<Entity>,
+    pub current: Entity,
+}
+
+fn focus_input(
+    keys: Option<Res<Input<KeyCode>>>,
+    gamepads: Option<Res<Gamepads>>,
+    buttons: Option<Res<Input<GamepadButton>>>,
+    mut moves: EventWriter<FocusMove>,
+) {
+    const DIRECTIONS: [(KeyCode, GamepadButtonType, FocusDirection); 4] = [
+        (KeyCode::Up, GamepadButtonType::DPadUp, FocusDirection::Up),
+        (
+            KeyCode::Down,
+            GamepadButtonType::DPadDown,
+            FocusDirection::Down,
+        ),
+        (
+            KeyCode::Left,
+            GamepadButtonType::DPadLeft,
+            FocusDirection::Left,
+        ),
+        (
+            KeyCode::Right,
+            GamepadButtonType::DPadRight,
+            FocusDirection::Right,
+        ),
+    ];
+    for (key, button, direction) in DIRECTIONS {
+        let key_pressed = keys.as_ref().map_or(false, |keys| keys.just_pressed(key));
+        let button_pressed = match (&gamepads, &buttons) {
+            (Some(gamepads), Some(buttons)) => gamepads
+                .iter()
+                .any(|gamepad| buttons.just_pressed(GamepadButton::new(gamepad, button))),
+            _ => false,
+        };
+        if key_pressed || button_pressed {
+            moves.send(FocusMove(direction));
+        }
+    }
+}
+
+fn navigate_focus(
+    mut commands: Commands,
+    mut moves: EventReader<FocusMove>,
+    mut changes: EventWriter<FocusChanged>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    focusables: Query<(Entity, &GlobalTransform, Option<&PickingFocused>), With<Focusable>>,
+) {
+    let directions: Vec<FocusDirection> = moves.iter().map(|focus_move| focus_move.0).collect();
+    if directions.is_empty() {
+        return;
+    }
+    let (camera, camera_transform) = match active_camera(cameras.iter()) {
+        Some(camera) => camera,
+        None => return,
+    };
+    let viewport_size = match camera.logical_viewport_size() {
+        Some(size) => size,
+        None => return,
+    };
+
+    // Entities behind the camera or outside of the viewport can not be focused,
+    // `world_to_viewport` only rejects those outside of the depth range
+    let positions: Vec<(Entity, Vec2)> = focusables
+        .iter()
+        .filter_map(|(entity, transform, _)| {
+            camera
+                .world_to_viewport(camera_transform, transform.translation())
+                .filter(|position| {
+                    position.cmpge(Vec2::ZERO).all() && position.cmplt(viewport_size).all()
+                })
+                .map(|position| (entity, position))
+        })
+        .collect();
+    let previous = focusables
+        .iter()
+        .find_map(|(entity, _, focused)| focused.map(|_| entity));
+
+    let mut current = previous.and_then(|previous| {
+        positions
+            .iter()
+            .find(|(entity, _)| *entity == previous)
+            .copied()
+    });
+    for direction in directions {
+        current = match current {
+            Some((entity, position)) => Some(
+                nearest_in_direction(&positions, entity, position, direction.vector())
+                    .unwrap_or((entity, position)),
+            ),
+            // The first move focuses the entity nearest to the center of the screen
+            None => nearest(&positions, viewport_size / 2.),
+        };
+    }
+
+    let current = match current {
+        Some((entity, _)) => entity,
+        None => return,
+    };
+    if previous == Some(current) {
+        return;
+    }
+    if let Some(previous) = previous {
+        commands.entity(previous).remove::<PickingFocused>();
+    }
+    commands.entity(current).insert(PickingFocused);
+    changes.send(FocusChanged { previous, current });
+}
+
+fn nearest(positions: &[(Entity, Vec2)], target: Vec2) -> Option<(Entity, Vec2)> {
+    positions
+        .iter()
+        .min_by(|(_, a), (_, b)| {
+            a.distance_squared(target)
+                .total_cmp(&b.distance_squared(target))
+        })
+        .copied()
+}
+
+/// The entity nearest to `from` in `direction`, preferring entities aligned with the direction
+fn nearest_in_direction(
+    positions: &[(Entity, Vec2)],
+    from_entity: Entity,
+    from: Vec2,
+    direction: Vec2,
+) -> Option<(Entity, Vec2)> {
+    positions
+        .iter()
+        .filter(|(entity, _)| *entity != from_entity)
+        .filter_map(|&(entity, position)| {
+            let offset = position - from;
+            let along = offset.dot(direction);
+            if along <= 0. {
+                return None;
+            }
+            let across = offset.perp_dot(direction).abs();
+            Some((entity, position, along + 2. * across))
+        })
+        .min_by(|(.., a), (.., b)| a.total_cmp(b))
+        .map(|(entity, position, _)| (entity, position))
+}
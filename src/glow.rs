@@ -0,0 +1,540 @@
+use bevy::{
+    core_pipeline::{core_3d::Camera3d, fullscreen_vertex_shader::fullscreen_shader_vertex_state},
+    ecs::query::QueryState,
+    prelude::{
+        Camera, Commands, Component, Entity, FromWorld, HandleUntyped, Msaa, Query, Res, ResMut,
+        Resource, Shader, With, World,
+    },
+    reflect::TypeUuid,
+    render::{
+        camera::ExtractedCamera,
+        render_graph::{Node, NodeRunError, RenderGraphContext, SlotInfo, SlotType},
+        render_phase::{
+            CachedRenderPipelinePhaseItem, DrawFunctionId, DrawFunctions, EntityPhaseItem,
+            PhaseItem, RenderPhase, TrackedRenderPass,
+        },
+        render_resource::{
+            encase, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
+            BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource, BindingType,
+            BlendState, Buffer, BufferBindingType, BufferInitDescriptor, BufferUsages,
+            CachedRenderPipelineId, ColorTargetState, ColorWrites, Extent3d, FragmentState, LoadOp,
+            MultisampleState, Operations, PipelineCache, PrimitiveState, RenderPassColorAttachment,
+            RenderPassDescriptor, RenderPipelineDescriptor, ShaderStages, ShaderType,
+            SpecializedRenderPipeline, SpecializedRenderPipelines, TextureDescriptor,
+            TextureDimension, TextureFormat, TextureSampleType, TextureUsages,
+            TextureViewDimension,
+        },
+        renderer::{RenderContext, RenderDevice},
+        texture::{CachedTexture, TextureCache},
+        view::ViewTarget,
+        Extract,
+    },
+    utils::FloatOrd,
+};
+
+use crate::OutlinePlugin;
+
+/// Fragment shader of the jump flood passes of glows
+pub(crate) const OUTLINE_GLOW_JUMP_FLOOD_SHADER_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(Shader::TYPE_UUID, 7053223528096556003);
+
+/// Fragment shader drawing glows from their distance field onto the view
+pub(crate) const OUTLINE_GLOW_COMPOSITE_SHADER_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(Shader::TYPE_UUID, 7053223528096556004);
+
+/// Pixel coordinates of the nearest glowing fragment, or -1 where there is none
+const SEED_FORMAT: TextureFormat = TextureFormat::Rg32Float;
+const COLOR_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+/// Width of the glow in physical pixels and its falloff
+const PARAMS_FORMAT: TextureFormat = TextureFormat::Rg16Float;
+
+/// Steps of the jump flood passes are powers of two up to `2^(JUMP_FLOOD_STEPS - 1)` pixels
+const JUMP_FLOOD_STEPS: usize = 16;
+
+/// Glowing outline drawn into the glow mask of a view.
+///
+/// The items are sorted back to front, so that the glow of the nearest mesh wins where
+/// glowing meshes overlap.
+pub struct OutlineGlowMask {
+    pub distance: f32,
+    pub entity: Entity,
+    pub pipeline: CachedRenderPipelineId,
+    pub draw_function: DrawFunctionId,
+}
+
+impl PhaseItem for OutlineGlowMask {
+    type SortKey = FloatOrd;
+
+    #[inline]
+    fn sort_key(&self) -> Self::SortKey {
+        FloatOrd(self.distance)
+    }
+
+    #[inline]
+    fn draw_function(&self) -> DrawFunctionId {
+        self.draw_function
+    }
+}
+
+impl EntityPhaseItem for OutlineGlowMask {
+    #[inline]
+    fn entity(&self) -> Entity {
+        self.entity
+    }
+}
+
+impl CachedRenderPipelinePhaseItem for OutlineGlowMask {
+    #[inline]
+    fn cached_pipeline(&self) -> CachedRenderPipelineId {
+        self.pipeline
+    }
+}
+
+/// Color targets of the glow mask: seed, inner color, outer color and glow parameters.
+/// The fragment entry point `glow_mask` of a glowing outline material writes them in this order.
+pub(crate) fn glow_mask_targets() -> Vec<Option<ColorTargetState>> {
+    [SEED_FORMAT, COLOR_FORMAT, COLOR_FORMAT, PARAMS_FORMAT]
+        .into_iter()
+        .map(|format| {
+            Some(ColorTargetState {
+                format,
+                blend: None,
+                write_mask: ColorWrites::ALL,
+            })
+        })
+        .collect()
+}
+
+pub(crate) fn extract_glow_phases(
+    mut commands: Commands,
+    cameras: Extract<Query<(Entity, &Camera), With<Camera3d>>>,
+) {
+    for (entity, camera) in cameras.iter() {
+        if camera.is_active {
+            commands
+                .get_or_spawn(entity)
+                .insert(RenderPhase::<OutlineGlowMask>::default());
+        }
+    }
+}
+
+#[derive(ShaderType)]
+struct JumpFloodStep {
+    step: i32,
+}
+
+#[derive(Resource)]
+pub(crate) struct OutlineGlowPipeline {
+    jump_flood_layout: BindGroupLayout,
+    composite_layout: BindGroupLayout,
+    jump_flood_pipeline: CachedRenderPipelineId,
+    /// Uniform buffer of each jump flood step, indexed by its power of two
+    steps: Vec<Buffer>,
+}
+
+fn texture_entry(binding: u32) -> BindGroupLayoutEntry {
+    BindGroupLayoutEntry {
+        binding,
+        visibility: ShaderStages::FRAGMENT,
+        ty: BindingType::Texture {
+            sample_type: TextureSampleType::Float { filterable: false },
+            view_dimension: TextureViewDimension::D2,
+            multisampled: false,
+        },
+        count: None,
+    }
+}
+
+impl FromWorld for OutlineGlowPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+
+        let jump_flood_layout =
+            render_device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: Some("outline_glow_jump_flood_layout"),
+                entries: &[
+                    texture_entry(0),
+                    BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: BindingType::Buffer {
+                            ty: BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: Some(JumpFloodStep::min_size()),
+                        },
+                        count: None,
+                    },
+                ],
+            });
+
+        let composite_layout = render_device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("outline_glow_composite_layout"),
+            entries: &[
+                texture_entry(0),
+                texture_entry(1),
+                texture_entry(2),
+                texture_entry(3),
+            ],
+        });
+
+        let steps = (0..JUMP_FLOOD_STEPS)
+            .map(|power| {
+                let mut contents = encase::UniformBuffer::new(Vec::new());
+                contents.write(&JumpFloodStep { step: 1 << power }).unwrap();
+                render_device.create_buffer_with_data(&BufferInitDescriptor {
+                    label: Some("outline_glow_jump_flood_step"),
+                    contents: contents.as_ref(),
+                    usage: BufferUsages::UNIFORM,
+                })
+            })
+            .collect();
+
+        let jump_flood_pipeline =
+            world
+                .resource_mut::<PipelineCache>()
+                .queue_render_pipeline(RenderPipelineDescriptor {
+                    label: Some("outline_glow_jump_flood_pipeline".into()),
+                    layout: Some(vec![jump_flood_layout.clone()]),
+                    vertex: fullscreen_shader_vertex_state(),
+                    fragment: Some(FragmentState {
+                        shader: OUTLINE_GLOW_JUMP_FLOOD_SHADER_HANDLE.typed::<Shader>(),
+                        shader_defs: Vec::new(),
+                        entry_point: "fragment".into(),
+                        targets: vec![Some(ColorTargetState {
+                            format: SEED_FORMAT,
+                            blend: None,
+                            write_mask: ColorWrites::ALL,
+                        })],
+                    }),
+                    primitive: PrimitiveState::default(),
+                    depth_stencil: None,
+                    multisample: MultisampleState::default(),
+                });
+
+        Self {
+            jump_flood_layout,
+            composite_layout,
+            jump_flood_pipeline,
+            steps,
+        }
+    }
+}
+
+/// Key of the pipeline drawing glows onto the main texture of a view
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct GlowCompositeKey {
+    samples: u32,
+    format: TextureFormat,
+}
+
+impl SpecializedRenderPipeline for OutlineGlowPipeline {
+    type Key = GlowCompositeKey;
+
+    fn specialize(&self, key: Self::Key) -> RenderPipelineDescriptor {
+        RenderPipelineDescriptor {
+            label: Some("outline_glow_composite_pipeline".into()),
+            layout: Some(vec![self.composite_layout.clone()]),
+            vertex: fullscreen_shader_vertex_state(),
+            fragment: Some(FragmentState {
+                shader: OUTLINE_GLOW_COMPOSITE_SHADER_HANDLE.typed::<Shader>(),
+                shader_defs: Vec::new(),
+                entry_point: "fragment".into(),
+                targets: vec![Some(ColorTargetState {
+                    format: key.format,
+                    blend: Some(BlendState::ALPHA_BLENDING),
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState {
+                count: key.samples,
+                ..Default::default()
+            },
+        }
+    }
+}
+
+/// Textures of the glow mask and distance field of a view
+#[derive(Component)]
+pub(crate) struct ViewGlowTextures {
+    /// The jump flood passes read from one and write to the other
+    seeds: [CachedTexture; 2],
+    inner_colors: CachedTexture,
+    outer_colors: CachedTexture,
+    params: CachedTexture,
+    size: Extent3d,
+}
+
+fn clear_attachment(
+    texture: &CachedTexture,
+    color: wgpu_types::Color,
+) -> Option<RenderPassColorAttachment> {
+    Some(RenderPassColorAttachment {
+        view: &texture.default_view,
+        resolve_target: None,
+        ops: Operations {
+            load: LoadOp::Clear(color),
+            store: true,
+        },
+    })
+}
+
+#[derive(Component)]
+pub(crate) struct ViewGlowPipeline(CachedRenderPipelineId);
+
+/// Allocate the glow textures and composite pipeline of the views with glowing outlines.
+/// Runs after queueing, so that views without glows cost nothing.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn prepare_glow_views(
+    mut commands: Commands,
+    mut texture_cache: ResMut<TextureCache>,
+    render_device: Res<RenderDevice>,
+    mut pipeline_cache: ResMut<PipelineCache>,
+    glow_pipeline: Res<OutlineGlowPipeline>,
+    mut pipelines: ResMut<SpecializedRenderPipelines<OutlineGlowPipeline>>,
+    msaa: Res<Msaa>,
+    views: Query<(
+        Entity,
+        &ExtractedCamera,
+        &ViewTarget,
+        &RenderPhase<OutlineGlowMask>,
+    )>,
+) {
+    for (entity, camera, target, phase) in views.iter() {
+        let size = match camera.physical_target_size {
+            Some(size) if !phase.items.is_empty() => size,
+            _ => continue,
+        };
+        let size = Extent3d {
+            width: size.x,
+            height: size.y,
+            depth_or_array_layers: 1,
+        };
+        let mut texture = |label: &'static str, format: TextureFormat| {
+            texture_cache.get(
+                &render_device,
+                TextureDescriptor {
+                    label: Some(label),
+                    size,
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: TextureDimension::D2,
+                    format,
+                    usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+                },
+            )
+        };
+        let textures = ViewGlowTextures {
+            seeds: [
+                texture("outline_glow_seeds_a", SEED_FORMAT),
+                texture("outline_glow_seeds_b", SEED_FORMAT),
+            ],
+            inner_colors: texture("outline_glow_inner_colors", COLOR_FORMAT),
+            outer_colors: texture("outline_glow_outer_colors", COLOR_FORMAT),
+            params: texture("outline_glow_params", PARAMS_FORMAT),
+            size,
+        };
+
+        let pipeline = pipelines.specialize(
+            &mut pipeline_cache,
+            &glow_pipeline,
+            GlowCompositeKey {
+                samples: msaa.samples,
+                format: target.main_texture_format(),
+            },
+        );
+
+        commands
+            .entity(entity)
+            .insert((textures, ViewGlowPipeline(pipeline)));
+    }
+}
+
+/// Render graph node drawing the glows of a view after its main pass.
+///
+/// The glowing meshes are drawn unextruded into a mask holding the pixel coordinates of each
+/// covered pixel, which jump flood passes spread into the coordinates of the nearest glowing
+/// pixel. The composite pass then blends each glow over the view within its width, using the
+/// colors of its nearest glowing pixel.
+pub(crate) struct OutlineGlowNode {
+    query: QueryState<(
+        &'static ExtractedCamera,
+        &'static RenderPhase<OutlineGlowMask>,
+        &'static ViewTarget,
+        &'static ViewGlowTextures,
+        &'static ViewGlowPipeline,
+    )>,
+}
+
+impl OutlineGlowNode {
+    pub(crate) const NAME: &'static str = "outline_glow";
+    pub(crate) const IN_VIEW: &'static str = "view";
+
+    pub(crate) fn new(world: &mut World) -> Self {
+        Self {
+            query: world.query(),
+        }
+    }
+}
+
+impl Node for OutlineGlowNode {
+    fn input(&self) -> Vec<SlotInfo> {
+        vec![SlotInfo::new(Self::IN_VIEW, SlotType::Entity)]
+    }
+
+    fn update(&mut self, world: &mut World) {
+        self.query.update_archetypes(world);
+    }
+
+    fn run(
+        &self,
+        graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let view_entity = graph.get_input_entity(Self::IN_VIEW)?;
+        let (camera, phase, target, textures, composite_pipeline) =
+            match self.query.get_manual(world, view_entity) {
+                Ok(result) => result,
+                // The view has no glowing outlines
+                Err(_) => return Ok(()),
+            };
+        if phase.items.is_empty() {
+            return Ok(());
+        }
+
+        let glow_pipeline = world.resource::<OutlineGlowPipeline>();
+        let pipeline_cache = world.resource::<PipelineCache>();
+        let (jump_flood_pipeline, composite_pipeline) = match (
+            pipeline_cache.get_render_pipeline(glow_pipeline.jump_flood_pipeline),
+            pipeline_cache.get_render_pipeline(composite_pipeline.0),
+        ) {
+            (Some(jump_flood), Some(composite)) => (jump_flood, composite),
+            _ => return Ok(()),
+        };
+
+        {
+            let no_seed = wgpu_types::Color {
+                r: -1.0,
+                g: -1.0,
+                b: 0.0,
+                a: 0.0,
+            };
+            let transparent = wgpu_types::Color::TRANSPARENT;
+            let mut mask_pass =
+                TrackedRenderPass::new(render_context.command_encoder.begin_render_pass(
+                    &RenderPassDescriptor {
+                        label: Some("outline_glow_mask_pass"),
+                        color_attachments: &[
+                            clear_attachment(&textures.seeds[0], no_seed),
+                            clear_attachment(&textures.inner_colors, transparent),
+                            clear_attachment(&textures.outer_colors, transparent),
+                            clear_attachment(&textures.params, transparent),
+                        ],
+                        depth_stencil_attachment: None,
+                    },
+                ));
+            if let Some(viewport) = camera.viewport.as_ref() {
+                mask_pass.set_camera_viewport(viewport);
+            }
+            let mut draw_functions = world.resource::<DrawFunctions<OutlineGlowMask>>().write();
+            for item in &phase.items {
+                let draw_function = draw_functions.get_mut(item.draw_function).unwrap();
+                draw_function.draw(world, &mut mask_pass, view_entity, item);
+            }
+        }
+
+        // The seeds only need to spread as far as the widest glow
+        let max_width = world.resource::<OutlinePlugin>().max_width;
+        let max_distance = max_width
+            .min(textures.size.width.max(textures.size.height) as f32)
+            .max(1.);
+        let mut step = (max_distance.ceil() as u32)
+            .next_power_of_two()
+            .min(1 << (JUMP_FLOOD_STEPS - 1));
+        let mut read = 0;
+        while step > 0 {
+            let bind_group = render_context
+                .render_device
+                .create_bind_group(&BindGroupDescriptor {
+                    label: Some("outline_glow_jump_flood_bind_group"),
+                    layout: &glow_pipeline.jump_flood_layout,
+                    entries: &[
+                        BindGroupEntry {
+                            binding: 0,
+                            resource: BindingResource::TextureView(
+                                &textures.seeds[read].default_view,
+                            ),
+                        },
+                        BindGroupEntry {
+                            binding: 1,
+                            resource: glow_pipeline.steps[step.trailing_zeros() as usize]
+                                .as_entire_binding(),
+                        },
+                    ],
+                });
+            let mut jump_flood_pass =
+                TrackedRenderPass::new(render_context.command_encoder.begin_render_pass(
+                    &RenderPassDescriptor {
+                        label: Some("outline_glow_jump_flood_pass"),
+                        color_attachments: &[Some(RenderPassColorAttachment {
+                            view: &textures.seeds[1 - read].default_view,
+                            resolve_target: None,
+                            ops: Operations::default(),
+                        })],
+                        depth_stencil_attachment: None,
+                    },
+                ));
+            jump_flood_pass.set_render_pipeline(jump_flood_pipeline);
+            jump_flood_pass.set_bind_group(0, &bind_group, &[]);
+            jump_flood_pass.draw(0..3, 0..1);
+            read = 1 - read;
+            step /= 2;
+        }
+
+        let bind_group = render_context
+            .render_device
+            .create_bind_group(&BindGroupDescriptor {
+                label: Some("outline_glow_composite_bind_group"),
+                layout: &glow_pipeline.composite_layout,
+                entries: &[
+                    BindGroupEntry {
+                        binding: 0,
+                        resource: BindingResource::TextureView(&textures.seeds[read].default_view),
+                    },
+                    BindGroupEntry {
+                        binding: 1,
+                        resource: BindingResource::TextureView(&textures.inner_colors.default_view),
+                    },
+                    BindGroupEntry {
+                        binding: 2,
+                        resource: BindingResource::TextureView(&textures.outer_colors.default_view),
+                    },
+                    BindGroupEntry {
+                        binding: 3,
+                        resource: BindingResource::TextureView(&textures.params.default_view),
+                    },
+                ],
+            });
+        let mut composite_pass =
+            TrackedRenderPass::new(render_context.command_encoder.begin_render_pass(
+                &RenderPassDescriptor {
+                    label: Some("outline_glow_composite_pass"),
+                    color_attachments: &[Some(target.get_color_attachment(Operations {
+                        load: LoadOp::Load,
+                        store: true,
+                    }))],
+                    depth_stencil_attachment: None,
+                },
+            ));
+        if let Some(viewport) = camera.viewport.as_ref() {
+            composite_pass.set_camera_viewport(viewport);
+        }
+        composite_pass.set_render_pipeline(composite_pipeline);
+        composite_pass.set_bind_group(0, &bind_group, &[]);
+        composite_pass.draw(0..3, 0..1);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,413 @@
+//! Outline highlights of pickable entities, independent of the picking backend.
+//!
+//! Picking integrations collect the active [`PickingState`]s of an entity and let
+//! [`HighlightOutlines`] pick the outline to show.
+
+use std::{cmp::Reverse, marker::PhantomData, ops::Deref};
+
+use bevy::{
+    ecs::{
+        query::WorldQuery,
+        system::{EntityCommands, SystemParam},
+    },
+    prelude::{
+        Added, App, Camera, Changed, Commands, Component, CoreStage, Deref, DerefMut, Entity,
+        GlobalTransform, Handle, Or, Query, RemovedComponents, Res, Resource, Without, World,
+    },
+    utils::{HashMap, HashSet},
+};
+
+use crate::OutlineMaterial;
+
+/// `OutlineMaterial` handle resource used when object is hovered.
+/// If this resource does not exist in world, no outline will show.
+#[derive(Resource, Deref)]
+pub struct HoverOutline(pub Handle<OutlineMaterial>);
+
+/// `OutlineMaterial` handle resource used when object is selected.
+/// If this resource does not exist in world, no outline will show.
+#[derive(Resource, Deref)]
+pub struct SelectedOutline(pub Handle<OutlineMaterial>);
+
+/// `OutlineMaterial` handle resource used when object is pressed or clicked.
+/// If this resource does not exist in world, no outline will show.
+#[derive(Resource, Deref)]
+pub struct PressedOutline(pub Handle<OutlineMaterial>);
+
+/// Highlight states of a pickable entity
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PickingState {
+    /// The entity is pressed or clicked
+    Pressed,
+    /// The cursor is over the entity
+    Hovered,
+    /// The entity is selected, or has a [`PickingSelected`] marker
+    Selected,
+    /// The entity has a [`PickingDisabled`] marker
+    Disabled,
+    /// The entity has a [`PickingFocused`] marker
+    Focused,
+    /// The entity has a [`PickingDragTarget`] marker
+    DragTarget,
+    /// A state of a user marker, see [`PickingStateMarker`]
+    Custom(&'static str),
+}
+
+/// Outline and priority of a [`PickingState`]
+#[derive(Clone)]
+pub struct StateOutline {
+    pub priority: i32,
+    /// Outline of the state. `Hovered`, `Pressed` and `Selected` fall back to
+    /// [`HoverOutline`], [`PressedOutline`] and [`SelectedOutline`] if `None`.
+    pub outline: Option<Handle<OutlineMaterial>>,
+}
+
+/// Mapping from picking states to outlines.
+///
+/// If several states of an entity are active, the one with the highest priority which has
+/// an outline is shown. States without an outline are ignored.
+/// By default `Disabled` > `DragTarget` > `Pressed` > `Hovered` > `Focused` > `Selected`.
+#[derive(Resource, Clone, Deref, DerefMut)]
+pub struct PickingOutlineStates(pub HashMap<PickingState, StateOutline>);
+
+impl Default for PickingOutlineStates {
+    fn default() -> Self {
+        Self(
+            [
+                (PickingState::Disabled, 50),
+                (PickingState::DragTarget, 40),
+                (PickingState::Pressed, 30),
+                (PickingState::Hovered, 20),
+                (PickingState::Focused, 15),
+                (PickingState::Selected, 10),
+            ]
+            .into_iter()
+            .map(|(state, priority)| {
+                (
+                    state,
+                    StateOutline {
+                        priority,
+                        outline: None,
+                    },
+                )
+            })
+            .collect(),
+        )
+    }
+}
+
+impl PickingOutlineStates {
+    /// Show `outline` for `state` with the given priority
+    pub fn with_state(
+        mut self,
+        state: PickingState,
+        priority: i32,
+        outline: Handle<OutlineMaterial>,
+    ) -> Self {
+        self.insert(
+            state,
+            StateOutline {
+                priority,
+                outline: Some(outline),
+            },
+        );
+        self
+    }
+
+    fn priority(&self, state: &PickingState) -> i32 {
+        self.get(state).map_or(0, |state| state.priority)
+    }
+}
+
+/// Marker components activating a [`PickingState`] while they are present on an entity.
+///
+/// Custom markers are registered with [`AddPickingStateMarker::add_picking_state_marker`].
+pub trait PickingStateMarker: Component {
+    const STATE: PickingState;
+}
+
+/// Marks an entity as disabled, see [`PickingState::Disabled`]
+#[derive(Component, Default)]
+pub struct PickingDisabled;
+
+impl PickingStateMarker for PickingDisabled {
+    const STATE: PickingState = PickingState::Disabled;
+}
+
+/// Marks an entity as focused, see [`PickingState::Focused`]
+#[derive(Component, Default)]
+pub struct PickingFocused;
+
+impl PickingStateMarker for PickingFocused {
+    const STATE: PickingState = PickingState::Focused;
+}
+
+/// Marks an entity as a possible target of a drag, see [`PickingState::DragTarget`]
+#[derive(Component, Default)]
+pub struct PickingDragTarget;
+
+impl PickingStateMarker for PickingDragTarget {
+    const STATE: PickingState = PickingState::DragTarget;
+}
+
+/// Marks an entity as selected, e.g. by [`OutlineSelectionPlugin`](crate::selection::OutlineSelectionPlugin),
+/// see [`PickingState::Selected`]
+#[derive(Component, Default)]
+pub struct PickingSelected;
+
+impl PickingStateMarker for PickingSelected {
+    const STATE: PickingState = PickingState::Selected;
+}
+
+/// Registers [`PickingStateMarker`]s
+pub trait AddPickingStateMarker {
+    fn add_picking_state_marker<C: PickingStateMarker>(&mut self) -> &mut Self;
+}
+
+impl AddPickingStateMarker for App {
+    fn add_picking_state_marker<C: PickingStateMarker>(&mut self) -> &mut Self {
+        // Removals are only visible until the end of the frame
+        self.add_system_to_stage(CoreStage::PostUpdate, sync_marker_state::<C>)
+    }
+}
+
+/// States of an entity activated by [`PickingStateMarker`]s
+#[derive(Component, Default, Deref, DerefMut)]
+pub struct MarkerStates(pub HashSet<PickingState>);
+
+fn sync_marker_state<C: PickingStateMarker>(
+    mut commands: Commands,
+    added: Query<Entity, Added<C>>,
+    removed: RemovedComponents<C>,
+    mut states: Query<&mut MarkerStates>,
+) {
+    for entity in added.iter() {
+        match states.get_mut(entity) {
+            Ok(mut states) => {
+                states.insert(C::STATE);
+            }
+            // Merge when the command is applied, as the markers added to the entity in the same
+            // frame would otherwise each insert their own `MarkerStates`
+            Err(_) => commands.add(move |world: &mut World| {
+                let mut entity = match world.get_entity_mut(entity) {
+                    Some(entity) if entity.contains::<C>() => entity,
+                    _ => return,
+                };
+                match entity.get_mut::<MarkerStates>() {
+                    Some(mut states) => {
+                        states.insert(C::STATE);
+                    }
+                    None => {
+                        entity.insert(MarkerStates([C::STATE].into_iter().collect()));
+                    }
+                }
+            }),
+        }
+    }
+    for entity in removed.iter() {
+        if let Ok(mut states) = states.get_mut(entity) {
+            states.remove(&C::STATE);
+        }
+    }
+}
+
+/// Per-entity outlines overriding [`HoverOutline`], [`PressedOutline`] and [`SelectedOutline`],
+/// e.g. red outlines for enemies and green ones for allies.
+/// Fields left `None` fall back to the global resource.
+#[derive(Component, Clone, Default)]
+pub struct PickingOutlines {
+    pub hover: Option<Handle<OutlineMaterial>>,
+    pub pressed: Option<Handle<OutlineMaterial>>,
+    pub selected: Option<Handle<OutlineMaterial>>,
+    /// Overrides of the other states configured in [`PickingOutlineStates`]
+    pub states: HashMap<PickingState, Handle<OutlineMaterial>>,
+}
+
+impl PickingOutlines {
+    pub fn get(&self, state: PickingState) -> Option<&Handle<OutlineMaterial>> {
+        match state {
+            PickingState::Hovered => self.hover.as_ref(),
+            PickingState::Pressed => self.pressed.as_ref(),
+            PickingState::Selected => self.selected.as_ref(),
+            state => self.states.get(&state),
+        }
+    }
+}
+
+/// The outline an entity had before it was highlighted by picking, e.g. a quest objective outline.
+///
+/// It is recorded when the highlight starts and restored when the highlight ends.
+/// To change the outline of a highlighted entity, change its `BaseOutline`.
+#[derive(Component, Clone, Deref)]
+pub struct BaseOutline(pub Handle<OutlineMaterial>);
+
+/// Marks entities whose `Handle<OutlineMaterial>` is a picking highlight
+#[derive(Component)]
+pub(crate) struct PickingHighlighted;
+
+/// Marks entities whose outline is resolved by a picking backend, including their marker states
+#[derive(Component)]
+pub(crate) struct PickingBackend;
+
+/// Add the resources and systems shared by all picking integrations, once
+pub(crate) fn add_highlighting(app: &mut App) {
+    if app.world.contains_resource::<PickingOutlineStates>() {
+        return;
+    }
+    app.init_resource::<PickingOutlineStates>()
+        .add_picking_state_marker::<PickingDisabled>()
+        .add_picking_state_marker::<PickingFocused>()
+        .add_picking_state_marker::<PickingDragTarget>()
+        .add_picking_state_marker::<PickingSelected>()
+        .add_system_to_stage(CoreStage::PostUpdate, marker_highlighting);
+}
+
+/// Add a picking backend handling the entities with a `C` component
+pub(crate) fn add_picking_backend<C: Component>(app: &mut App) {
+    add_highlighting(app);
+    app.add_system_to_stage(CoreStage::PostUpdate, mark_picking_backend::<C>);
+}
+
+fn mark_picking_backend<C: Component>(mut commands: Commands, added: Query<Entity, Added<C>>) {
+    for entity in added.iter() {
+        commands.entity(entity).insert(PickingBackend);
+    }
+}
+
+/// Outline entities without picking backend from their marker states only
+#[allow(clippy::type_complexity)]
+fn marker_highlighting(
+    mut commands: Commands,
+    outlines: HighlightOutlines,
+    query: Query<
+        (
+            Entity,
+            &MarkerStates,
+            Option<&PickingOutlines>,
+            OutlineState,
+        ),
+        (
+            Without<PickingBackend>,
+            Or<(Changed<MarkerStates>, Changed<PickingOutlines>)>,
+        ),
+    >,
+) {
+    for (entity, markers, overrides, state) in query.iter() {
+        let outline = outlines.outline(markers.iter().copied().collect(), overrides);
+        set_highlight(&mut commands.entity(entity), outline, &state);
+    }
+}
+
+/// Resources deciding which outline a pickable entity shows
+#[derive(SystemParam)]
+pub(crate) struct HighlightOutlines<'w, 's> {
+    states: Res<'w, PickingOutlineStates>,
+    hover_outline: Option<Res<'w, HoverOutline>>,
+    pressed_outline: Option<Res<'w, PressedOutline>>,
+    selected_outline: Option<Res<'w, SelectedOutline>>,
+    #[system_param(ignore)]
+    marker: PhantomData<&'s ()>,
+}
+
+impl<'w, 's> HighlightOutlines<'w, 's> {
+    /// The outline of the active state with the highest priority, if any
+    pub fn outline(
+        &self,
+        mut active: Vec<PickingState>,
+        overrides: Option<&PickingOutlines>,
+    ) -> Option<Handle<OutlineMaterial>> {
+        active.sort_by_key(|state| Reverse(self.states.priority(state)));
+        active.into_iter().find_map(|state| {
+            overrides
+                .and_then(|overrides| overrides.get(state))
+                .or_else(|| {
+                    self.states
+                        .get(&state)
+                        .and_then(|state| state.outline.as_ref())
+                })
+                .or_else(|| match state {
+                    PickingState::Hovered => global_outline(&self.hover_outline),
+                    PickingState::Pressed => global_outline(&self.pressed_outline),
+                    PickingState::Selected => global_outline(&self.selected_outline),
+                    _ => None,
+                })
+                .cloned()
+        })
+    }
+}
+
+/// The active camera with the highest priority, which picking integrations pick with
+pub(crate) fn active_camera<'a>(
+    cameras: impl IntoIterator<Item = (&'a Camera, &'a GlobalTransform)>,
+) -> Option<(&'a Camera, &'a GlobalTransform)> {
+    cameras
+        .into_iter()
+        .filter(|(camera, _)| camera.is_active)
+        .max_by_key(|(camera, _)| camera.priority)
+}
+
+#[inline]
+fn global_outline<T: Deref<Target = Handle<OutlineMaterial>> + Resource>(
+    outline: &Option<Res<T>>,
+) -> Option<&Handle<OutlineMaterial>> {
+    outline.as_ref().map(|outline| &***outline)
+}
+
+/// Outline components of a pickable entity
+#[derive(WorldQuery)]
+pub(crate) struct OutlineState {
+    current: Option<&'static Handle<OutlineMaterial>>,
+    base: Option<&'static BaseOutline>,
+    highlighted: Option<&'static PickingHighlighted>,
+}
+
+/// Show `outline` as highlight, or end the highlight if it is `None`
+#[inline]
+pub(crate) fn set_highlight(
+    entity_commands: &mut EntityCommands,
+    outline: Option<Handle<OutlineMaterial>>,
+    state: &OutlineStateItem,
+) {
+    match outline {
+        Some(outline) => highlight(entity_commands, outline, state),
+        None => restore_outline(entity_commands, state),
+    }
+}
+
+/// Show a picking highlight, remembering the outline the entity had before
+#[inline]
+fn highlight(
+    entity_commands: &mut EntityCommands,
+    outline: Handle<OutlineMaterial>,
+    state: &OutlineStateItem,
+) {
+    if state.highlighted.is_none() {
+        // Remember the outline placed by the game
+        match state.current {
+            Some(current) => {
+                entity_commands.insert(BaseOutline(current.clone()));
+            }
+            None => {
+                entity_commands.remove::<BaseOutline>();
+            }
+        }
+    }
+    entity_commands.insert((outline, PickingHighlighted));
+}
+
+/// End the picking highlight of an entity and restore its [`BaseOutline`]
+#[inline]
+fn restore_outline(entity_commands: &mut EntityCommands, state: &OutlineStateItem) {
+    if state.highlighted.is_none() {
+        return;
+    }
+    match state.base {
+        Some(base) => {
+            entity_commands.insert(base.0.clone());
+        }
+        None => {
+            entity_commands.remove::<Handle<OutlineMaterial>>();
+        }
+    }
+    entity_commands.remove::<(BaseOutline, PickingHighlighted)>();
+}
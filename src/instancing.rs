@@ -0,0 +1,173 @@
+use std::{marker::PhantomData, ops::Range};
+
+use bevy::{
+    ecs::system::{
+        lifetimeless::{Read, SQuery, SRes},
+        SystemParamItem,
+    },
+    math::Mat4,
+    prelude::{Component, Entity, Handle, Mesh, Resource},
+    render::{
+        mesh::GpuBufferInfo,
+        render_asset::RenderAssets,
+        render_phase::{EntityRenderCommand, RenderCommandResult, TrackedRenderPass},
+        render_resource::{
+            BufferUsages, BufferVec, VertexAttribute, VertexBufferLayout, VertexFormat,
+            VertexStepMode,
+        },
+        renderer::{RenderDevice, RenderQueue},
+    },
+    utils::HashMap,
+};
+
+use crate::{diagnostics::OutlineStats, warnings::OutlineWarnings, OutlineMaterialTrait};
+
+/// Instance ranges of an instanced outline draw in the [`OutlineInstanceBuffer`], keyed by view.
+///
+/// It is inserted on the first entity of a batch of outlines sharing mesh, material and layer.
+#[derive(Component)]
+pub struct OutlineInstances<M: OutlineMaterialTrait> {
+    pub(crate) views: HashMap<Entity, Range<u32>>,
+    marker: PhantomData<M>,
+}
+
+impl<M: OutlineMaterialTrait> Default for OutlineInstances<M> {
+    fn default() -> Self {
+        Self {
+            views: HashMap::default(),
+            marker: PhantomData,
+        }
+    }
+}
+
+/// Model matrices of all instanced outlines of a material type in the current frame.
+///
+/// The GPU buffer is kept across frames and only reallocated when it has to grow.
+#[derive(Resource)]
+pub struct OutlineInstanceBuffer<M: OutlineMaterialTrait> {
+    models: BufferVec<[f32; 16]>,
+    marker: PhantomData<M>,
+}
+
+impl<M: OutlineMaterialTrait> Default for OutlineInstanceBuffer<M> {
+    fn default() -> Self {
+        let mut models = BufferVec::new(BufferUsages::VERTEX);
+        models.set_label(Some("outline instance buffer"));
+        Self {
+            models,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<M: OutlineMaterialTrait> OutlineInstanceBuffer<M> {
+    pub(crate) fn clear(&mut self) {
+        self.models.clear();
+    }
+
+    /// Appends the model matrices of a batch, returns their instance range
+    pub(crate) fn push(&mut self, transforms: &[Mat4]) -> Range<u32> {
+        let start = self.models.len() as u32;
+        for transform in transforms {
+            self.models.push(transform.to_cols_array());
+        }
+        start..self.models.len() as u32
+    }
+
+    pub(crate) fn write_buffer(
+        &mut self,
+        render_device: &RenderDevice,
+        render_queue: &RenderQueue,
+    ) {
+        self.models.write_buffer(render_device, render_queue);
+    }
+}
+
+/// Layout of the per-instance vertex buffer, bound to locations 3 to 6 under the `INSTANCED` def
+pub(crate) fn instance_buffer_layout() -> VertexBufferLayout {
+    VertexBufferLayout {
+        array_stride: std::mem::size_of::<[f32; 16]>() as u64,
+        step_mode: VertexStepMode::Instance,
+        attributes: (0..4)
+            .map(|column| VertexAttribute {
+                format: VertexFormat::Float32x4,
+                offset: column * std::mem::size_of::<[f32; 4]>() as u64,
+                shader_location: 3 + column as u32,
+            })
+            .collect(),
+    }
+}
+
+/// Draws the mesh of an outline, once per instance if the entity carries [`OutlineInstances`]
+pub struct DrawOutlineMesh<M: OutlineMaterialTrait>(PhantomData<M>);
+impl<M: OutlineMaterialTrait> EntityRenderCommand for DrawOutlineMesh<M> {
+    type Param = (
+        SRes<RenderAssets<Mesh>>,
+        SQuery<(Read<Handle<Mesh>>, Option<Read<OutlineInstances<M>>>)>,
+        SRes<OutlineInstanceBuffer<M>>,
+        SRes<OutlineWarnings>,
+        Option<SRes<OutlineStats>>,
+    );
+
+    fn render<'w>(
+        view: Entity,
+        item: Entity,
+        (meshes, query, instance_buffer, warnings, stats): SystemParamItem<'w, '_, Self::Param>,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        let (mesh_handle, instances) = match query.get_inner(item) {
+            Ok(components) => components,
+            Err(_) => {
+                warnings.warn_once(item, "Outline mesh handle is missing");
+                return RenderCommandResult::Failure;
+            }
+        };
+        let gpu_mesh = match meshes.into_inner().get(mesh_handle) {
+            Some(gpu_mesh) => gpu_mesh,
+            None => {
+                warnings.warn_once(item, "Outline mesh is not prepared");
+                return RenderCommandResult::Failure;
+            }
+        };
+
+        let instances: Range<u32> = match instances.and_then(|instances| instances.views.get(&view))
+        {
+            Some(range) => match instance_buffer.into_inner().models.buffer() {
+                Some(buffer) => {
+                    // Offset the buffer rather than the instances, as a first instance other
+                    // than 0 is not supported everywhere
+                    let stride = std::mem::size_of::<[f32; 16]>() as u64;
+                    pass.set_vertex_buffer(
+                        1,
+                        buffer.slice(range.start as u64 * stride..range.end as u64 * stride),
+                    );
+                    0..range.len() as u32
+                }
+                None => {
+                    warnings.warn_once(item, "Outline instance buffer is not written");
+                    return RenderCommandResult::Failure;
+                }
+            },
+            None => 0..1,
+        };
+
+        pass.set_vertex_buffer(0, gpu_mesh.vertex_buffer.slice(..));
+        match &gpu_mesh.buffer_info {
+            GpuBufferInfo::Indexed {
+                buffer,
+                index_format,
+                count,
+            } => {
+                pass.set_index_buffer(buffer.slice(..), 0, *index_format);
+                pass.draw_indexed(0..*count, 0, instances);
+            }
+            GpuBufferInfo::NonIndexed { vertex_count } => {
+                pass.draw(0..*vertex_count, instances);
+            }
+        }
+        if let Some(stats) = stats {
+            stats.record_draw_call();
+        }
+        RenderCommandResult::Success
+    }
+}
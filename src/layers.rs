@@ -0,0 +1,94 @@
+use std::cmp::Ordering;
+
+use bevy::{
+    pbr::MeshUniform,
+    prelude::{
+        Assets, Commands, Component, ComputedVisibility, Deref, DerefMut, FromReflect,
+        GlobalTransform, Handle, Local, Mesh, Query, Reflect, ReflectComponent, Res,
+    },
+    render::Extract,
+};
+
+use crate::{OutlineMaterial, OutlineSettings};
+
+/// Stacked outlines of an entity, e.g. a thin white outline inside a thick black one.
+///
+/// Every layer is drawn as a separate outline. Layers are sorted by `order` first and then by
+/// width, so that wider layers are drawn behind narrower ones.
+#[derive(Component, Clone, Default, Deref, DerefMut, Reflect)]
+#[reflect(Component)]
+pub struct Outlines(pub Vec<OutlineLayer>);
+
+/// One layer of [`Outlines`]
+#[derive(Clone, Default, Reflect, FromReflect)]
+pub struct OutlineLayer {
+    pub material: Handle<OutlineMaterial>,
+    /// Layers with a lower order are drawn behind layers with a higher order
+    pub order: i32,
+}
+
+impl OutlineLayer {
+    pub fn new(material: Handle<OutlineMaterial>) -> Self {
+        Self { material, order: 0 }
+    }
+}
+
+/// Index of an outline layer drawn by a render world entity, counted from the back
+#[derive(Component, Clone, Copy)]
+pub(crate) struct OutlineLayerIndex(pub u8);
+
+/// Spawn one render world entity per outline layer
+pub(crate) fn extract_outline_layers(
+    mut commands: Commands,
+    mut prev_len: Local<usize>,
+    settings: Extract<Res<OutlineSettings>>,
+    materials: Extract<Res<Assets<OutlineMaterial>>>,
+    query: Extract<
+        Query<(
+            &ComputedVisibility,
+            &GlobalTransform,
+            &Handle<Mesh>,
+            &Outlines,
+        )>,
+    >,
+) {
+    if !settings.enabled {
+        return;
+    }
+
+    let width = |layer: &OutlineLayer| {
+        materials
+            .get(&layer.material)
+            .map_or(0., |material| material.width)
+    };
+
+    let mut layer_entities = Vec::with_capacity(*prev_len);
+    for (visibility, transform, mesh_handle, outlines) in query.iter() {
+        if !visibility.is_visible() {
+            continue;
+        }
+
+        let mut layers: Vec<_> = outlines.iter().collect();
+        layers.sort_by(|a, b| match a.order.cmp(&b.order) {
+            Ordering::Equal => width(b).total_cmp(&width(a)),
+            ordering => ordering,
+        });
+
+        let transform = transform.compute_matrix();
+        let mesh_uniform = MeshUniform {
+            transform,
+            inverse_transpose_model: transform.inverse().transpose(),
+            flags: 0,
+        };
+        for (index, layer) in layers.into_iter().enumerate() {
+            layer_entities.push((
+                mesh_handle.clone_weak(),
+                mesh_uniform.clone(),
+                layer.material.clone_weak(),
+                OutlineLayerIndex(index.min(u8::MAX as usize) as u8),
+            ));
+        }
+    }
+    *prev_len = layer_entities.len();
+    commands.spawn_batch(layer_entities);
+}
@@ -1,56 +1,99 @@
 #![doc = include_str!("../README.md")]
 
+mod diagnostics;
+mod events;
+pub mod focus;
+mod glow;
+pub mod highlight;
+mod instancing;
+mod layers;
+mod loader;
+mod material;
+mod palette;
+pub mod pointer_events;
 mod prepare;
+pub mod selection;
+mod settings;
 mod smooth_normal;
+mod uniforms;
+mod vertex_width;
+mod warnings;
 mod window_size;
 
 #[cfg(feature = "picking")]
 pub mod picking;
+#[cfg(feature = "raycast_picking")]
+pub mod raycast;
 
-use std::mem::size_of;
+use std::{cmp::Reverse, hash::Hash, marker::PhantomData, num::NonZeroU64};
 
 use bevy::{
-    core_pipeline::core_3d::Opaque3d,
-    ecs::system::{
-        lifetimeless::{Read, SQuery, SRes},
-        SystemParamItem,
-    },
+    asset::HandleId,
+    core_pipeline::core_3d::{self, Opaque3d, Transparent3d},
     pbr::{
-        DrawMesh, MeshPipeline, MeshPipelineKey, MeshUniform, SetMeshBindGroup,
+        AlphaMode, MeshPipeline, MeshPipelineKey, MeshUniform, SetMeshBindGroup,
         SetMeshViewBindGroup,
     },
     prelude::*,
     reflect::TypeUuid,
     render::{
-        extract_component::ExtractComponentPlugin,
+        extract_resource::ExtractResourcePlugin,
         mesh::{MeshVertexAttribute, MeshVertexBufferLayout},
-        render_asset::{PrepareAssetError, RenderAsset, RenderAssetPlugin, RenderAssets},
-        render_phase::{
-            AddRenderCommand, DrawFunctions, EntityRenderCommand, RenderCommandResult, RenderPhase,
-            SetItemPipeline, TrackedRenderPass,
-        },
+        render_asset::RenderAssets,
+        render_graph::RenderGraph,
+        render_phase::{sort_phase_system, DrawFunctions, RenderPhase, SetItemPipeline},
         render_resource::{
-            encase, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
-            BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingType, BlendState,
-            BufferBindingType, BufferDescriptor, BufferInitDescriptor, CompareFunction,
-            DepthBiasState, DepthStencilState, Face, FragmentState, FrontFace, MultisampleState,
-            PipelineCache, PolygonMode, PrimitiveState, RenderPipelineDescriptor, ShaderStages,
-            ShaderType, SpecializedMeshPipeline, SpecializedMeshPipelineError,
-            SpecializedMeshPipelines, StencilFaceState, StencilState, TextureFormat, VertexState,
+            AsBindGroup, BindGroupLayout, BindGroupLayoutDescriptor, BindGroupLayoutEntry,
+            BindingType, BlendState, BufferBindingType, CompareFunction, DepthBiasState,
+            DepthStencilState, DynamicUniformBuffer, Face, FragmentState, FrontFace,
+            MultisampleState, PipelineCache, PolygonMode, PrimitiveState, RenderPipelineDescriptor,
+            ShaderRef, ShaderStages, ShaderType, SpecializedMeshPipeline,
+            SpecializedMeshPipelineError, SpecializedMeshPipelines, SpecializedRenderPipelines,
+            StencilFaceState, StencilState, TextureFormat, VertexState,
         },
-        renderer::RenderDevice,
+        renderer::{RenderDevice, RenderQueue},
         texture::BevyDefault,
         view::ExtractedView,
         RenderApp, RenderStage,
     },
+    utils::{FloatOrd, HashMap},
 };
-use wgpu_types::{BufferUsages, ColorTargetState, ColorWrites, VertexFormat};
+use serde::{Deserialize, Serialize};
+use wgpu_types::{ColorTargetState, ColorWrites, VertexFormat};
 use window_size::{DoubleReciprocalWindowSizeUniform, SetWindowSizeBindGroup};
 
+pub use diagnostics::{OutlineDiagnosticsPlugin, OutlineViewDiagnostics};
+pub use events::{OutlineAdded, OutlineRemoved, OutlineStyleChanged};
+pub use glow::OutlineGlowMask;
+pub use layers::{OutlineLayer, Outlines};
+pub use loader::OutlineMaterialLoader;
+pub use material::{
+    OutlineMaterialPlugin, OutlineMaterialTrait, OutlinePipelineKey, PreparedOutlineMaterial,
+    RenderOutlineMaterials, SetOutlineMaterialBindGroup,
+};
+pub use palette::{OutlinePalette, PaletteRemap};
+pub use settings::OutlineSettings;
+pub use uniforms::{OutlineUniformBuffer, SharedOutlineUniform};
+pub use vertex_width::{
+    outline_width_from_curvature, outline_width_from_vertex_colors, VertexColorChannel,
+};
+
 use crate::{
+    diagnostics::OutlineStats,
+    events::{send_layer_events, send_outline_events},
+    glow::{
+        extract_glow_phases, glow_mask_targets, prepare_glow_views, OutlineGlowNode,
+        OutlineGlowPipeline, OUTLINE_GLOW_COMPOSITE_SHADER_HANDLE,
+        OUTLINE_GLOW_JUMP_FLOOD_SHADER_HANDLE,
+    },
+    instancing::{
+        instance_buffer_layout, DrawOutlineMesh, OutlineInstanceBuffer, OutlineInstances,
+    },
+    layers::{extract_outline_layers, OutlineLayerIndex},
     prepare::prepare_outline_mesh,
+    warnings::{prune_outline_warnings, OutlineWarnings},
     window_size::{
-        extract_window_size, prepare_window_size, queue_window_size_bind_group,
+        extract_scale_factors, prepare_window_size, queue_window_size_bind_group,
         DoubleReciprocalWindowSizeMeta,
     },
 };
@@ -62,14 +105,80 @@ macro_rules! load_internal_asset {
     }};
 }
 
+/// Vertex and fragment shaders of [`OutlineMaterial`], which only work with each other
 pub const OUTLINE_SHADER_HANDLE: HandleUntyped =
     HandleUntyped::weak_from_u64(Shader::TYPE_UUID, 7053223528096556000);
 
+/// Shader module imported as `bevy_outline::outline_functions` by outline shaders
+pub const OUTLINE_FUNCTIONS_SHADER_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(Shader::TYPE_UUID, 7053223528096556001);
+
+/// Default shaders of [`OutlineMaterialTrait`], reading only the `width` and `color`
+/// at the start of the material uniform
+pub const OUTLINE_BASIC_SHADER_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(Shader::TYPE_UUID, 7053223528096556002);
+
 pub const ATTRIBUTE_OUTLINE_NORMAL: MeshVertexAttribute =
     MeshVertexAttribute::new("OutlineNormal", 9885409170, VertexFormat::Float32x3);
 
+/// Optional per-vertex factor multiplied into the outline width.
+/// The outline pipeline only reads it when the mesh contains it.
+pub const ATTRIBUTE_OUTLINE_WIDTH: MeshVertexAttribute =
+    MeshVertexAttribute::new("OutlineWidth", 9885409171, VertexFormat::Float32);
+
 /// Plugin which enables outline shader
-pub struct OutlinePlugin;
+///
+/// The plugin is also inserted as a resource into the app and render app, so that its settings
+/// can be read by the outline systems.
+#[derive(Resource, Clone, Debug)]
+pub struct OutlinePlugin {
+    /// The render phase outlines are drawn in
+    pub render_phase: OutlineRenderPhase,
+    /// How opaque outlines behave when MSAA is enabled
+    pub msaa_behavior: OutlineMsaa,
+    /// Compute `ATTRIBUTE_OUTLINE_NORMAL` for outlined meshes which lack it.
+    /// Disable this if the outline normals are baked offline.
+    pub auto_generate_normals: bool,
+    /// Upper bound of the outline width in pixels, applied to all outline materials
+    pub max_width: f32,
+    /// Mode of the outline materials whose `mode` is `None`
+    pub default_mode: OutlineMode,
+}
+
+impl Default for OutlinePlugin {
+    fn default() -> Self {
+        Self {
+            render_phase: OutlineRenderPhase::Auto,
+            msaa_behavior: OutlineMsaa::Inherit,
+            auto_generate_normals: true,
+            max_width: f32::MAX,
+            default_mode: OutlineMode::Solid,
+        }
+    }
+}
+
+/// The render phase outlines are drawn in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutlineRenderPhase {
+    /// Draw outlines with `AlphaMode::Blend` in `Transparent3d` and all others in `Opaque3d`
+    #[default]
+    Auto,
+    /// Draw all outlines in `Opaque3d`, ignoring their alpha
+    Opaque,
+    /// Draw all outlines in `Transparent3d` with alpha blending
+    Transparent,
+}
+
+/// How opaque outlines behave when MSAA is enabled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutlineMsaa {
+    /// Use the multisampling of the view without further changes
+    #[default]
+    Inherit,
+    /// Turn the alpha of opaque outlines into MSAA coverage, which gives order independent
+    /// translucency when combined with `OutlineRenderPhase::Opaque`
+    AlphaToCoverage,
+}
 
 impl Plugin for OutlinePlugin {
     fn build(&self, app: &mut App) {
@@ -79,106 +188,355 @@ impl Plugin for OutlinePlugin {
             "render/outline.wgsl",
             Shader::from_wgsl
         );
+        load_internal_asset!(
+            app,
+            OUTLINE_BASIC_SHADER_HANDLE,
+            "render/outline_basic.wgsl",
+            Shader::from_wgsl
+        );
+        load_internal_asset!(
+            app,
+            OUTLINE_FUNCTIONS_SHADER_HANDLE,
+            "render/outline_functions.wgsl",
+            Shader::from_wgsl
+        );
+        load_internal_asset!(
+            app,
+            OUTLINE_GLOW_JUMP_FLOOD_SHADER_HANDLE,
+            "render/glow_jump_flood.wgsl",
+            Shader::from_wgsl
+        );
+        load_internal_asset!(
+            app,
+            OUTLINE_GLOW_COMPOSITE_SHADER_HANDLE,
+            "render/glow_composite.wgsl",
+            Shader::from_wgsl
+        );
 
         let render_device = app.world.resource::<RenderDevice>();
-        let buffer = render_device.create_buffer(&BufferDescriptor {
-            label: Some("window size uniform buffer"),
-            size: size_of::<DoubleReciprocalWindowSizeUniform>() as u64,
-            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
-            mapped_at_creation: false,
+        let layout = render_device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("window size layout"),
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::VERTEX | ShaderStages::FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: true,
+                    min_binding_size: Some(DoubleReciprocalWindowSizeUniform::min_size()),
+                },
+                count: None,
+            }],
         });
 
-        app.add_asset::<OutlineMaterial>()
-            .add_plugin(ExtractComponentPlugin::<Handle<OutlineMaterial>>::default())
-            .add_plugin(RenderAssetPlugin::<OutlineMaterial>::default())
-            .add_system_to_stage(CoreStage::PostUpdate, prepare_outline_mesh);
+        app.insert_resource(self.clone())
+            .register_type::<OutlineMaterial>()
+            .register_type::<OutlineDash>()
+            .register_type::<Option<OutlineDash>>()
+            .register_type::<OutlineMode>()
+            .register_type::<Option<OutlineMode>>()
+            .register_type::<OutlineUnit>()
+            .register_type::<Handle<OutlineMaterial>>()
+            .register_type::<Outlines>()
+            .register_type::<OutlineLayer>()
+            .register_type::<Vec<OutlineLayer>>()
+            .register_type::<OutlineSettings>()
+            .register_type::<OutlinePalette>()
+            .register_type::<PaletteRemap>()
+            .init_resource::<OutlineSettings>()
+            .init_resource::<OutlinePalette>()
+            .add_plugin(ExtractResourcePlugin::<OutlineSettings>::default())
+            .add_event::<OutlineAdded>()
+            .add_event::<OutlineRemoved>()
+            .add_event::<OutlineStyleChanged>()
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                prepare_outline_mesh::<With<Outlines>>,
+            )
+            .add_system_to_stage(CoreStage::Last, send_outline_events)
+            .add_system_to_stage(CoreStage::Last, send_layer_events);
 
         if let Ok(render_app) = app.get_sub_app_mut(RenderApp) {
             render_app
-                .add_render_command::<Opaque3d, DrawOutlines>()
+                .insert_resource(self.clone())
                 .insert_resource(DoubleReciprocalWindowSizeMeta {
-                    buffer,
+                    buffer: DynamicUniformBuffer::default(),
+                    layout,
                     bind_group: None,
                 })
-                .init_resource::<OutlinePipeline>()
-                .init_resource::<SpecializedMeshPipelines<OutlinePipeline>>()
-                .add_system_to_stage(RenderStage::Extract, extract_window_size)
+                .init_resource::<OutlineWarnings>()
+                .init_resource::<DrawFunctions<OutlineGlowMask>>()
+                .init_resource::<OutlineGlowPipeline>()
+                .init_resource::<SpecializedRenderPipelines<OutlineGlowPipeline>>()
+                .add_system_to_stage(RenderStage::Extract, extract_scale_factors)
+                .add_system_to_stage(RenderStage::Extract, prune_outline_warnings)
+                .add_system_to_stage(RenderStage::Extract, extract_outline_layers)
+                .add_system_to_stage(RenderStage::Extract, extract_glow_phases)
                 .add_system_to_stage(RenderStage::Prepare, prepare_window_size)
-                .add_system_to_stage(RenderStage::Queue, queue_outlines)
-                .add_system_to_stage(RenderStage::Queue, queue_window_size_bind_group);
+                .add_system_to_stage(RenderStage::Queue, queue_window_size_bind_group)
+                .add_system_to_stage(RenderStage::PhaseSort, sort_phase_system::<OutlineGlowMask>)
+                .add_system_to_stage(RenderStage::PhaseSort, prepare_glow_views);
+
+            let glow_node = OutlineGlowNode::new(&mut render_app.world);
+            let mut graph = render_app.world.resource_mut::<RenderGraph>();
+            let draw_3d_graph = graph.get_sub_graph_mut(core_3d::graph::NAME).unwrap();
+            draw_3d_graph.add_node(OutlineGlowNode::NAME, glow_node);
+            draw_3d_graph
+                .add_slot_edge(
+                    draw_3d_graph.input_node().unwrap().id,
+                    core_3d::graph::input::VIEW_ENTITY,
+                    OutlineGlowNode::NAME,
+                    OutlineGlowNode::IN_VIEW,
+                )
+                .unwrap();
+            // MAIN_PASS -> OUTLINE_GLOW -> TONEMAPPING
+            draw_3d_graph
+                .add_node_edge(core_3d::graph::node::MAIN_PASS, OutlineGlowNode::NAME)
+                .unwrap();
+            draw_3d_graph
+                .add_node_edge(OutlineGlowNode::NAME, core_3d::graph::node::TONEMAPPING)
+                .unwrap();
         }
+
+        app.add_plugin(OutlineMaterialPlugin::<OutlineMaterial>::default())
+            .add_asset_loader(OutlineMaterialLoader);
     }
 }
 
-#[derive(TypeUuid, Clone)]
+/// The default outline material: a solid or dashed color outline with a width in physical pixels
+///
+/// It can be loaded from `.outline.ron` files, see [`OutlineMaterialLoader`].
+#[derive(AsBindGroup, TypeUuid, Clone, Debug, Reflect, FromReflect, Serialize, Deserialize)]
 #[uuid = "f31fac68-fd87-44db-a4c5-eed0bcbb96cd"]
+#[uniform(0, OutlineMaterialUniform)]
+#[reflect(Default)]
+#[serde(default)]
 pub struct OutlineMaterial {
     pub width: f32,
+    /// Color of the outline, or the inner color of a glow
     pub color: Color,
+    /// Draw the outline as dashes instead of a solid line
+    pub dash: Option<OutlineDash>,
+    /// How the outline is shaded, `None` uses `OutlinePlugin::default_mode`
+    pub mode: Option<OutlineMode>,
+    /// Unit of `width` and of the dash pattern
+    pub unit: OutlineUnit,
 }
 
-#[derive(ShaderType)]
-struct OutlineMaterialUniform {
-    width: f32,
-    color: Vec4,
+impl Default for OutlineMaterial {
+    fn default() -> Self {
+        Self {
+            width: 5.,
+            color: Color::BLACK,
+            dash: None,
+            mode: None,
+            unit: OutlineUnit::PhysicalPixels,
+        }
+    }
 }
 
-pub struct GpuOutlineMaterial {
-    bind_group: BindGroup,
-}
+impl OutlineMaterialTrait for OutlineMaterial {
+    fn vertex_shader() -> ShaderRef {
+        OUTLINE_SHADER_HANDLE.typed::<Shader>().into()
+    }
 
-impl RenderAsset for OutlineMaterial {
-    type ExtractedAsset = OutlineMaterial;
-    type PreparedAsset = GpuOutlineMaterial;
-    type Param = (SRes<RenderDevice>, SRes<OutlinePipeline>);
+    fn fragment_shader() -> ShaderRef {
+        OUTLINE_SHADER_HANDLE.typed::<Shader>().into()
+    }
 
-    fn extract_asset(&self) -> Self::ExtractedAsset {
-        self.clone()
+    fn instancing() -> bool {
+        true
     }
 
-    fn prepare_asset(
-        extracted_asset: Self::ExtractedAsset,
-        (render_device, pipeline): &mut SystemParamItem<Self::Param>,
-    ) -> Result<Self::PreparedAsset, PrepareAssetError<Self::ExtractedAsset>> {
-        let uniform = OutlineMaterialUniform {
-            width: extracted_asset.width,
-            color: extracted_asset.color.as_linear_rgba_f32().into(),
-        };
+    fn glow(&self) -> bool {
+        matches!(self.mode, Some(OutlineMode::Glow { .. }))
+    }
 
-        let byte_buffer = [0u8; size_of::<OutlineMaterialUniform>()];
-        let mut buffer = encase::UniformBuffer::new(byte_buffer);
-        buffer.write(&uniform).unwrap();
+    fn apply_defaults(&mut self, config: &OutlinePlugin) {
+        self.mode.get_or_insert(config.default_mode);
+    }
 
-        let buffer = render_device.create_buffer_with_data(&BufferInitDescriptor {
-            label: None,
-            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
-            contents: buffer.as_ref(),
-        });
+    fn apply_palette(&mut self, palette: &OutlinePalette) {
+        self.color = palette.remap_color(self.color);
+        if let Some(OutlineDash {
+            gap_color: Some(gap_color),
+            ..
+        }) = &mut self.dash
+        {
+            *gap_color = palette.remap_color(*gap_color);
+        }
+        if let Some(OutlineMode::Glow { outer_color, .. }) = &mut self.mode {
+            *outer_color = palette.remap_color(*outer_color);
+        }
+    }
 
-        let bind_group = render_device.create_bind_group(&BindGroupDescriptor {
-            label: None,
-            layout: &pipeline.material_layout,
-            entries: &[BindGroupEntry {
-                binding: 0,
-                resource: buffer.as_entire_binding(),
-            }],
-        });
-        Ok(GpuOutlineMaterial { bind_group })
+    fn shared_uniform_size() -> Option<NonZeroU64> {
+        Some(OutlineMaterialUniform::min_size())
+    }
+
+    fn shared_uniform(&self) -> Option<SharedOutlineUniform<Self>> {
+        Some(SharedOutlineUniform::new(
+            (),
+            &OutlineMaterialUniform::from(self),
+        ))
+    }
+
+    fn alpha_mode(&self) -> AlphaMode {
+        let translucent_gap = matches!(
+            self.dash,
+            Some(OutlineDash {
+                gap_color: Some(gap_color),
+                ..
+            }) if gap_color.a() < 1.
+        );
+        if self.color.a() < 1. || translucent_gap {
+            AlphaMode::Blend
+        } else {
+            AlphaMode::Opaque
+        }
+    }
+}
+
+/// How the outline hull is shaded
+#[derive(Debug, Clone, Copy, PartialEq, Default, Reflect, FromReflect, Serialize, Deserialize)]
+pub enum OutlineMode {
+    /// Fill the outline with a single color
+    #[default]
+    Solid,
+    /// Blend from `color` at the silhouette to `outer_color` at `width` pixels from it,
+    /// shaped by `falloff` as an exponent of the distance.
+    ///
+    /// Glows are blended over the view after its main pass, from a distance field of the
+    /// silhouettes of all glowing meshes in the view. They are not occluded by nearer meshes,
+    /// ignore `dash` and `OutlinePlugin::render_phase`, and where glows overlap the one of
+    /// the nearest mesh wins.
+    Glow { outer_color: Color, falloff: f32 },
+}
+
+/// Unit of the width of an outline
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Default, Reflect, FromReflect, Serialize, Deserialize,
+)]
+pub enum OutlineUnit {
+    /// Pixels of the render target, so outlines get thinner on HiDPI displays
+    #[default]
+    PhysicalPixels,
+    /// Pixels scaled by the scale factor of the window, so outlines keep their size on HiDPI displays
+    LogicalPixels,
+}
+
+/// Dash pattern of an outline, measured in pixels along the screen-space silhouette
+#[derive(Debug, Clone, Copy, PartialEq, Reflect, FromReflect, Serialize, Deserialize)]
+pub struct OutlineDash {
+    /// Length of a dash in pixels
+    pub length: f32,
+    /// Length of the gap between two dashes in pixels
+    pub gap: f32,
+    /// Speed of the pattern along the silhouette in pixels per second
+    pub speed: f32,
+    /// Color filling the gaps. Gaps are transparent if `None`
+    pub gap_color: Option<Color>,
+}
+
+impl OutlineDash {
+    /// A static dash pattern with transparent gaps
+    pub fn new(length: f32, gap: f32) -> Self {
+        Self {
+            length,
+            gap,
+            speed: 0.,
+            gap_color: None,
+        }
+    }
+
+    /// The animated "marching ants" pattern usually used for selection feedback
+    pub fn marching_ants(length: f32, gap_color: Color) -> Self {
+        Self {
+            length,
+            gap: length,
+            speed: length * 4.,
+            gap_color: Some(gap_color),
+        }
     }
 }
 
+#[derive(ShaderType)]
+struct OutlineMaterialUniform {
+    width: f32,
+    color: Vec4,
+    dash_length: f32,
+    dash_gap: f32,
+    dash_speed: f32,
+    flags: u32,
+    gap_color: Vec4,
+    glow_color: Vec4,
+    glow_falloff: f32,
+}
+
+const OUTLINE_FLAGS_DASHED_BIT: u32 = 1;
+const OUTLINE_FLAGS_GAP_COLOR_BIT: u32 = 2;
+const OUTLINE_FLAGS_LOGICAL_PIXELS_BIT: u32 = 8;
+
+impl From<&OutlineMaterial> for OutlineMaterialUniform {
+    fn from(material: &OutlineMaterial) -> Self {
+        let mut uniform = OutlineMaterialUniform {
+            width: material.width,
+            color: material.color.as_linear_rgba_f32().into(),
+            dash_length: 0.,
+            dash_gap: 0.,
+            dash_speed: 0.,
+            flags: 0,
+            gap_color: Vec4::ZERO,
+            glow_color: Vec4::ZERO,
+            glow_falloff: 1.,
+        };
+        if let Some(dash) = material.dash {
+            uniform.dash_length = dash.length;
+            uniform.dash_gap = dash.gap;
+            uniform.dash_speed = dash.speed;
+            uniform.flags |= OUTLINE_FLAGS_DASHED_BIT;
+            if let Some(gap_color) = dash.gap_color {
+                uniform.gap_color = gap_color.as_linear_rgba_f32().into();
+                uniform.flags |= OUTLINE_FLAGS_GAP_COLOR_BIT;
+            }
+        }
+        if let Some(OutlineMode::Glow {
+            outer_color,
+            falloff,
+        }) = material.mode
+        {
+            uniform.glow_color = outer_color.as_linear_rgba_f32().into();
+            uniform.glow_falloff = falloff;
+        }
+        if material.unit == OutlineUnit::LogicalPixels {
+            uniform.flags |= OUTLINE_FLAGS_LOGICAL_PIXELS_BIT;
+        }
+        uniform
+    }
+}
+
+const LAYER_DEPTH_BIAS: i32 = 2;
+const LAYER_DEPTH_BIAS_SLOPE_SCALE: f32 = 1.0;
+
+/// Render pipeline data for a given [`OutlineMaterialTrait`].
 #[derive(Resource)]
-pub struct OutlinePipeline {
+pub struct OutlinePipeline<M: OutlineMaterialTrait> {
     pub mesh_layout: BindGroupLayout,
     pub view_layout: BindGroupLayout,
     pub material_layout: BindGroupLayout,
+    /// Layout of the shared uniform buffer, see [`OutlineMaterialTrait::shared_uniform`]
+    pub shared_material_layout: Option<BindGroupLayout>,
     pub window_size_layout: BindGroupLayout,
+    pub vertex_shader: Handle<Shader>,
+    pub fragment_shader: Handle<Shader>,
+    pub msaa_behavior: OutlineMsaa,
+    marker: PhantomData<M>,
 }
 
-impl FromWorld for OutlinePipeline {
+impl<M: OutlineMaterialTrait> FromWorld for OutlinePipeline<M> {
     fn from_world(render_world: &mut World) -> Self {
         let mesh_pipeline = render_world.resource::<MeshPipeline>();
         let render_device = render_world.resource::<RenderDevice>();
+        let asset_server = render_world.resource::<AssetServer>();
         let mesh_binding = BindGroupLayoutEntry {
             binding: 0,
             visibility: ShaderStages::VERTEX | ShaderStages::FRAGMENT,
@@ -197,80 +555,117 @@ impl FromWorld for OutlinePipeline {
 
         let view_layout = mesh_pipeline.view_layout.clone();
 
-        let material_layout = render_device.create_bind_group_layout(&BindGroupLayoutDescriptor {
-            label: Some("material layout"),
-            entries: &[BindGroupLayoutEntry {
-                binding: 0,
-                visibility: ShaderStages::VERTEX | ShaderStages::FRAGMENT,
-                ty: BindingType::Buffer {
-                    ty: BufferBindingType::Uniform,
-                    has_dynamic_offset: false,
-                    min_binding_size: Some(OutlineMaterialUniform::min_size()),
-                },
-                count: None,
-            }],
-        });
+        let material_layout = M::bind_group_layout(render_device);
 
-        let window_size_layout =
+        let shared_material_layout = M::shared_uniform_size().map(|size| {
             render_device.create_bind_group_layout(&BindGroupLayoutDescriptor {
-                label: Some("window size layout"),
                 entries: &[BindGroupLayoutEntry {
                     binding: 0,
-                    visibility: ShaderStages::VERTEX,
+                    visibility: ShaderStages::VERTEX | ShaderStages::FRAGMENT,
                     ty: BindingType::Buffer {
                         ty: BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: Some(DoubleReciprocalWindowSizeUniform::min_size()),
+                        has_dynamic_offset: true,
+                        min_binding_size: Some(size),
                     },
                     count: None,
                 }],
-            });
+                label: Some("shared_outline_material_layout"),
+            })
+        });
+
+        let window_size_layout = render_world
+            .resource::<DoubleReciprocalWindowSizeMeta>()
+            .layout
+            .clone();
+
+        let msaa_behavior = render_world.resource::<OutlinePlugin>().msaa_behavior;
+
+        let load_shader = |shader_ref: ShaderRef| match shader_ref {
+            ShaderRef::Default => OUTLINE_BASIC_SHADER_HANDLE.typed::<Shader>(),
+            ShaderRef::Handle(handle) => handle,
+            ShaderRef::Path(path) => asset_server.load(path),
+        };
 
         Self {
             mesh_layout,
             view_layout,
             material_layout,
+            shared_material_layout,
             window_size_layout,
+            vertex_shader: load_shader(M::vertex_shader()),
+            fragment_shader: load_shader(M::fragment_shader()),
+            msaa_behavior,
+            marker: PhantomData,
         }
     }
 }
 
-impl SpecializedMeshPipeline for OutlinePipeline {
-    type Key = MeshPipelineKey;
+impl<M: OutlineMaterialTrait> SpecializedMeshPipeline for OutlinePipeline<M>
+where
+    M::Data: PartialEq + Eq + Hash + Clone,
+{
+    type Key = OutlinePipelineKey<M>;
 
     fn specialize(
         &self,
         key: Self::Key,
         layout: &MeshVertexBufferLayout,
     ) -> Result<RenderPipelineDescriptor, SpecializedMeshPipelineError> {
-        let vertex_attributes = vec![
+        let mut shader_defs = Vec::new();
+        let mut vertex_attributes = vec![
             Mesh::ATTRIBUTE_POSITION.at_shader_location(0),
             ATTRIBUTE_OUTLINE_NORMAL.at_shader_location(1),
         ];
 
-        let vertex_buffer_layout = layout.get_layout(&vertex_attributes)?;
+        if layout.contains(ATTRIBUTE_OUTLINE_WIDTH) {
+            shader_defs.push(String::from("VERTEX_OUTLINE_WIDTH"));
+            vertex_attributes.push(ATTRIBUTE_OUTLINE_WIDTH.at_shader_location(2));
+        }
+
+        if key.glow_mask {
+            shader_defs.push(String::from("OUTLINE_GLOW_MASK"));
+        }
+
+        let mut buffers = vec![layout.get_layout(&vertex_attributes)?];
+        if key.instanced {
+            shader_defs.push(String::from("INSTANCED"));
+            buffers.push(instance_buffer_layout());
+        }
 
+        // Translucent outlines are blended over what is behind them and must not occlude it
+        let transparent = key
+            .mesh_key
+            .contains(MeshPipelineKey::TRANSPARENT_MAIN_PASS);
+
+        let material_layout = match &self.shared_material_layout {
+            Some(layout) if key.shared_uniform => layout.clone(),
+            _ => self.material_layout.clone(),
+        };
         let bind_group_layout = vec![
             self.view_layout.clone(),
             self.mesh_layout.clone(),
-            self.material_layout.clone(),
+            material_layout,
             self.window_size_layout.clone(),
         ];
 
-        Ok(RenderPipelineDescriptor {
+        let mut descriptor = RenderPipelineDescriptor {
             vertex: VertexState {
-                shader: OUTLINE_SHADER_HANDLE.typed::<Shader>(),
+                shader: self.vertex_shader.clone(),
                 entry_point: "vertex".into(),
-                shader_defs: vec![],
-                buffers: vec![vertex_buffer_layout],
+                shader_defs: shader_defs.clone(),
+                buffers,
             },
             fragment: Some(FragmentState {
-                shader: OUTLINE_SHADER_HANDLE.typed::<Shader>(),
-                shader_defs: vec![],
+                shader: self.fragment_shader.clone(),
+                shader_defs,
                 entry_point: "fragment".into(),
                 targets: vec![Some(ColorTargetState {
                     format: TextureFormat::bevy_default(),
-                    blend: Some(BlendState::REPLACE),
+                    blend: Some(if transparent {
+                        BlendState::ALPHA_BLENDING
+                    } else {
+                        BlendState::REPLACE
+                    }),
                     write_mask: ColorWrites::ALL,
                 })],
             }),
@@ -281,12 +676,12 @@ impl SpecializedMeshPipeline for OutlinePipeline {
                 unclipped_depth: false,
                 polygon_mode: PolygonMode::Fill,
                 conservative: false,
-                topology: key.primitive_topology(),
+                topology: key.mesh_key.primitive_topology(),
                 strip_index_format: None,
             },
             depth_stencil: Some(DepthStencilState {
                 format: TextureFormat::Depth32Float,
-                depth_write_enabled: true,
+                depth_write_enabled: !transparent,
                 depth_compare: CompareFunction::Greater,
                 stencil: StencilState {
                     front: StencilFaceState::IGNORE,
@@ -294,95 +689,291 @@ impl SpecializedMeshPipeline for OutlinePipeline {
                     read_mask: 0,
                     write_mask: 0,
                 },
+                // Pull the outline layers in front of the layers behind them
                 bias: DepthBiasState {
-                    constant: 0,
-                    slope_scale: 0.0,
+                    constant: key.layer as i32 * LAYER_DEPTH_BIAS,
+                    slope_scale: key.layer as f32 * LAYER_DEPTH_BIAS_SLOPE_SCALE,
                     clamp: 0.0,
                 },
             }),
             multisample: MultisampleState {
-                count: key.msaa_samples(),
+                count: key.mesh_key.msaa_samples(),
                 mask: !0,
-                alpha_to_coverage_enabled: false,
+                alpha_to_coverage_enabled: !transparent
+                    && key.mesh_key.msaa_samples() > 1
+                    && self.msaa_behavior == OutlineMsaa::AlphaToCoverage,
             },
             label: Some("outline_mesh_pipeline".into()),
-        })
+        };
+
+        if key.glow_mask {
+            // Glowing meshes are drawn unextruded into the glow mask of the view, see `glow.rs`
+            let fragment = descriptor.fragment.as_mut().unwrap();
+            fragment.entry_point = "glow_mask".into();
+            fragment.targets = glow_mask_targets();
+            descriptor.primitive.cull_mode = None;
+            descriptor.depth_stencil = None;
+            descriptor.label = Some("outline_glow_mask_pipeline".into());
+        }
+
+        M::specialize(self, &mut descriptor, layout, key)?;
+        Ok(descriptor)
     }
 }
 
+/// Opaque outlines sharing mesh, material and layer in a view, drawn as one instanced draw call
+struct OutlineBatch<'a, M: OutlineMaterialTrait> {
+    entity: Entity,
+    key: OutlinePipelineKey<M>,
+    mesh_layout: &'a MeshVertexBufferLayout,
+    distance: f32,
+    transforms: Vec<Mat4>,
+}
+
 #[allow(clippy::too_many_arguments)]
 #[allow(clippy::type_complexity)]
-fn queue_outlines(
+fn queue_outlines<M: OutlineMaterialTrait>(
+    mut commands: Commands,
     opaque_3d_draw_functions: Res<DrawFunctions<Opaque3d>>,
+    transparent_3d_draw_functions: Res<DrawFunctions<Transparent3d>>,
+    glow_draw_functions: Res<DrawFunctions<OutlineGlowMask>>,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    mut instance_buffer: ResMut<OutlineInstanceBuffer<M>>,
     render_meshes: Res<RenderAssets<Mesh>>,
-    outline_pipeline: Res<OutlinePipeline>,
-    mut pipelines: ResMut<SpecializedMeshPipelines<OutlinePipeline>>,
+    render_materials: Res<RenderOutlineMaterials<M>>,
+    outline_pipeline: Res<OutlinePipeline<M>>,
+    mut pipelines: ResMut<SpecializedMeshPipelines<OutlinePipeline<M>>>,
     mut pipeline_cache: ResMut<PipelineCache>,
     msaa: Res<Msaa>,
-    material_meshes: Query<(Entity, &Handle<Mesh>, &MeshUniform), With<Handle<OutlineMaterial>>>,
-    mut views: Query<(&ExtractedView, &mut RenderPhase<Opaque3d>)>,
-) {
-    let draw_function = opaque_3d_draw_functions
+    config: Res<OutlinePlugin>,
+    settings: Res<OutlineSettings>,
+    stats: Option<Res<OutlineStats>>,
+    material_meshes: Query<(
+        Entity,
+        &Handle<M>,
+        &Handle<Mesh>,
+        &MeshUniform,
+        Option<&OutlineLayerIndex>,
+    )>,
+    mut views: Query<(
+        Entity,
+        &ExtractedView,
+        &mut RenderPhase<Opaque3d>,
+        &mut RenderPhase<Transparent3d>,
+        &mut RenderPhase<OutlineGlowMask>,
+    )>,
+) where
+    M::Data: PartialEq + Eq + Hash + Clone,
+{
+    if !settings.enabled {
+        return;
+    }
+
+    let draw_opaque = opaque_3d_draw_functions
+        .read()
+        .get_id::<DrawOutlines<M>>()
+        .unwrap();
+    let draw_transparent = transparent_3d_draw_functions
         .read()
-        .get_id::<DrawOutlines>()
+        .get_id::<DrawOutlines<M>>()
+        .unwrap();
+    let draw_glow = glow_draw_functions
+        .read()
+        .get_id::<DrawOutlines<M>>()
         .unwrap();
 
     let msaa_key = MeshPipelineKey::from_msaa_samples(msaa.samples);
+    let mut instances: HashMap<Entity, OutlineInstances<M>> = HashMap::default();
+    instance_buffer.clear();
 
-    for (view, mut opaque_phase) in views.iter_mut() {
+    for (view_entity, view, mut opaque_phase, mut transparent_phase, mut glow_phase) in
+        views.iter_mut()
+    {
         let inverse_view_matrix = view.transform.compute_matrix().inverse();
         let view_row_2 = inverse_view_matrix.row(2);
 
-        for (entity, mesh_handle, mesh_uniform) in material_meshes.iter() {
-            if let Some(mesh) = render_meshes.get(mesh_handle) {
-                let key =
-                    msaa_key | MeshPipelineKey::from_primitive_topology(mesh.primitive_topology);
+        // Skip the outlines whose material or mesh is not prepared yet before applying the cap
+        let mut outlines: Vec<_> = material_meshes
+            .iter()
+            .filter_map(
+                |(entity, material_handle, mesh_handle, mesh_uniform, layer)| {
+                    Some((
+                        entity,
+                        material_handle,
+                        render_materials.get(material_handle)?,
+                        mesh_handle,
+                        render_meshes.get(mesh_handle)?,
+                        mesh_uniform,
+                        layer,
+                    ))
+                },
+            )
+            .collect();
+        if let Some(max_outlines) = settings.max_outlines {
+            // Keep the outlines nearest to the camera
+            outlines.sort_by_cached_key(|(.., mesh_uniform, _)| {
+                Reverse(FloatOrd(view_row_2.dot(mesh_uniform.transform.col(3))))
+            });
+            outlines.truncate(max_outlines);
+        }
+
+        let mut batches: HashMap<(HandleId, HandleId, u8), OutlineBatch<M>> = HashMap::default();
+        let mut queued = 0;
+        let mut queued_pipelines = Vec::new();
+        for (entity, material_handle, material, mesh_handle, mesh, mesh_uniform, layer) in outlines
+        {
+            let layer = layer.map_or(0, |layer| layer.0);
+            if material.glow {
+                // The glow mask is not multisampled
+                let key = OutlinePipelineKey {
+                    mesh_key: MeshPipelineKey::from_msaa_samples(1)
+                        | MeshPipelineKey::from_primitive_topology(mesh.primitive_topology),
+                    bind_group_data: material.key.clone(),
+                    layer,
+                    instanced: false,
+                    glow_mask: true,
+                    shared_uniform: material.uniform_offset.is_some(),
+                };
                 let pipeline =
                     pipelines.specialize(&mut pipeline_cache, &outline_pipeline, key, &mesh.layout);
                 let pipeline = match pipeline {
                     Ok(id) => id,
                     Err(err) => {
                         error!("{}", err);
-                        return;
+                        continue;
                     }
                 };
+                queued += 1;
+                queued_pipelines.push(pipeline);
+                glow_phase.add(OutlineGlowMask {
+                    entity,
+                    pipeline,
+                    draw_function: draw_glow,
+                    distance: view_row_2.dot(mesh_uniform.transform.col(3)),
+                });
+                continue;
+            }
+
+            let transparent = match config.render_phase {
+                OutlineRenderPhase::Auto => material.alpha_mode == AlphaMode::Blend,
+                OutlineRenderPhase::Opaque => false,
+                OutlineRenderPhase::Transparent => true,
+            };
+            let mut mesh_key =
+                msaa_key | MeshPipelineKey::from_primitive_topology(mesh.primitive_topology);
+            if transparent {
+                mesh_key |= MeshPipelineKey::TRANSPARENT_MAIN_PASS;
+            }
+            let key = OutlinePipelineKey {
+                mesh_key,
+                bind_group_data: material.key.clone(),
+                layer,
+                instanced: false,
+                glow_mask: false,
+                shared_uniform: material.uniform_offset.is_some(),
+            };
+
+            // Opaque outlines are batched and queued below
+            if !transparent && M::instancing() {
+                // Follow the Opaque3d distance calculation.
+                let distance = -view_row_2.dot(mesh_uniform.transform.col(3)) + 0.0001;
+                let batch = batches
+                    .entry((mesh_handle.id(), material_handle.id(), layer))
+                    .or_insert_with(|| OutlineBatch {
+                        entity,
+                        key,
+                        mesh_layout: &mesh.layout,
+                        distance,
+                        transforms: Vec::new(),
+                    });
+                batch.distance = batch.distance.min(distance);
+                batch.transforms.push(mesh_uniform.transform);
+                continue;
+            }
+
+            let pipeline =
+                pipelines.specialize(&mut pipeline_cache, &outline_pipeline, key, &mesh.layout);
+            let pipeline = match pipeline {
+                Ok(id) => id,
+                Err(err) => {
+                    error!("{}", err);
+                    continue;
+                }
+            };
+            queued += 1;
+            queued_pipelines.push(pipeline);
+            if transparent {
+                // Sort slightly behind the outlined mesh so that a transparent mesh
+                // is blended over its own outline, and wider layers behind narrower ones.
+                let layer_offset = 0.0001 / (1. + layer as f32);
+                let distance = view_row_2.dot(mesh_uniform.transform.col(3)) - layer_offset;
+                transparent_phase.add(Transparent3d {
+                    entity,
+                    pipeline,
+                    draw_function: draw_transparent,
+                    distance,
+                });
+            } else {
                 // Follow the Opaque3d distance calculation.
                 let distance = -view_row_2.dot(mesh_uniform.transform.col(3)) + 0.0001;
                 opaque_phase.add(Opaque3d {
                     entity,
                     pipeline,
-                    draw_function,
+                    draw_function: draw_opaque,
                     distance,
-                })
+                });
             }
         }
+
+        for (_, mut batch) in batches {
+            // A single outline is drawn with the mesh uniform as usual
+            batch.key.instanced = batch.transforms.len() > 1;
+            let pipeline = pipelines.specialize(
+                &mut pipeline_cache,
+                &outline_pipeline,
+                batch.key,
+                batch.mesh_layout,
+            );
+            let pipeline = match pipeline {
+                Ok(id) => id,
+                Err(err) => {
+                    error!("{}", err);
+                    continue;
+                }
+            };
+            queued += batch.transforms.len();
+            queued_pipelines.push(pipeline);
+            if batch.transforms.len() > 1 {
+                let range = instance_buffer.push(&batch.transforms);
+                instances
+                    .entry(batch.entity)
+                    .or_default()
+                    .views
+                    .insert(view_entity, range);
+            }
+            opaque_phase.add(Opaque3d {
+                entity: batch.entity,
+                pipeline,
+                draw_function: draw_opaque,
+                distance: batch.distance,
+            });
+        }
+
+        if let Some(stats) = &stats {
+            stats.record_queued(view_entity, queued, queued_pipelines);
+        }
     }
+
+    instance_buffer.write_buffer(&render_device, &render_queue);
+    commands.insert_or_spawn_batch(instances);
 }
 
-type DrawOutlines = (
+type DrawOutlines<M> = (
     SetItemPipeline,
     SetMeshViewBindGroup<0>,
     SetMeshBindGroup<1>,
-    SetOutlineMaterialBindGroup<2>,
+    SetOutlineMaterialBindGroup<M, 2>,
     SetWindowSizeBindGroup<3>,
-    DrawMesh,
+    DrawOutlineMesh<M>,
 );
-
-struct SetOutlineMaterialBindGroup<const I: usize>;
-impl<const I: usize> EntityRenderCommand for SetOutlineMaterialBindGroup<I> {
-    type Param = (
-        SRes<RenderAssets<OutlineMaterial>>,
-        SQuery<Read<Handle<OutlineMaterial>>>,
-    );
-    fn render<'w>(
-        _view: Entity,
-        item: Entity,
-        (materials, query): SystemParamItem<'w, '_, Self::Param>,
-        pass: &mut TrackedRenderPass<'w>,
-    ) -> RenderCommandResult {
-        let material_handle = query.get(item).unwrap();
-        let material = materials.into_inner().get(material_handle).unwrap();
-        pass.set_bind_group(I, &material.bind_group, &[]);
-        RenderCommandResult::Success
-    }
-}
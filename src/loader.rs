@@ -0,0 +1,48 @@
+use std::ffi::OsStr;
+
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    utils::BoxedFuture,
+};
+
+use crate::OutlineMaterial;
+
+/// Loads [`OutlineMaterial`]s from `.outline.ron` or `.outline.json` files describing
+/// an outline style.
+///
+/// With `AssetPlugin::watch_for_changes` enabled, edited style files are reloaded and
+/// the outlines using them are updated while the app is running.
+///
+/// Fields which are left out keep their default value:
+///
+/// ```ron
+/// (
+///     width: 8.,
+///     color: Rgba(red: 1., green: 0.8, blue: 0., alpha: 1.),
+///     dash: Some((length: 6., gap: 6., speed: 24., gap_color: None)),
+/// )
+/// ```
+#[derive(Default)]
+pub struct OutlineMaterialLoader;
+
+impl AssetLoader for OutlineMaterialLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let material: OutlineMaterial =
+                match load_context.path().extension().and_then(OsStr::to_str) {
+                    Some("json") => serde_json::from_slice(bytes)?,
+                    _ => ron::de::from_bytes(bytes)?,
+                };
+            load_context.set_default_asset(LoadedAsset::new(material));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["outline.ron", "outline.json"]
+    }
+}
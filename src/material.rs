@@ -0,0 +1,480 @@
+use std::{hash::Hash, marker::PhantomData, num::NonZeroU64};
+
+use bevy::{
+    core_pipeline::core_3d::{Opaque3d, Transparent3d},
+    ecs::system::{
+        lifetimeless::{Read, SQuery, SRes},
+        SystemParamItem,
+    },
+    pbr::MeshPipelineKey,
+    prelude::*,
+    reflect::TypeUuid,
+    render::{
+        extract_component::ExtractComponentPlugin,
+        mesh::MeshVertexBufferLayout,
+        render_asset::{PrepareAssetLabel, RenderAssets},
+        render_phase::{
+            AddRenderCommand, EntityRenderCommand, RenderCommandResult, TrackedRenderPass,
+        },
+        render_resource::{
+            AsBindGroup, AsBindGroupError, BindGroup, OwnedBindingResource,
+            RenderPipelineDescriptor, ShaderRef, SpecializedMeshPipelineError,
+            SpecializedMeshPipelines,
+        },
+        renderer::{RenderDevice, RenderQueue},
+        texture::FallbackImage,
+        Extract, RenderApp, RenderStage,
+    },
+    utils::{HashMap, HashSet},
+};
+
+use crate::{
+    instancing::OutlineInstanceBuffer,
+    prepare::prepare_outline_mesh,
+    queue_outlines,
+    uniforms::{OutlineUniformBuffer, SharedOutlineUniform},
+    warnings::OutlineWarnings,
+    DrawOutlines, OutlineGlowMask, OutlinePalette, OutlinePipeline, OutlinePlugin, OutlineSettings,
+};
+
+/// Outline materials are used alongside [`OutlineMaterialPlugin`] to draw outlines of meshes
+/// with custom shader logic, similar to bevy's `Material`.
+///
+/// The pipeline binds the view at group 0, the mesh at group 1, the material at group 2
+/// and the window size at group 3.
+/// Shaders can `#import bevy_outline::outline_functions` to reuse the extrusion of the default
+/// vertex shader.
+///
+/// The default shaders read a uniform at binding 0 of the material which starts with
+/// `width: f32` in physical pixels and `color: vec4<f32>`. The default fragment shader reads no
+/// vertex outputs, so it can follow any custom vertex shader which outputs
+/// `@builtin(position)`. A custom fragment shader reads whatever `@location`s its vertex shader
+/// outputs, so one that follows the default vertex shader can only read `@builtin(position)`.
+///
+/// The shaders of [`OutlineMaterial`](crate::OutlineMaterial) at
+/// [`OUTLINE_SHADER_HANDLE`](crate::OUTLINE_SHADER_HANDLE) only work with each other
+/// and its uniform.
+pub trait OutlineMaterialTrait:
+    AsBindGroup + Send + Sync + Clone + TypeUuid + Sized + 'static
+{
+    /// Returns this material's vertex shader. If [`ShaderRef::Default`] is returned,
+    /// the default outline vertex shader will be used.
+    fn vertex_shader() -> ShaderRef {
+        ShaderRef::Default
+    }
+
+    /// Returns this material's fragment shader. If [`ShaderRef::Default`] is returned,
+    /// the default outline fragment shader will be used, which draws the uniform `color`.
+    fn fragment_shader() -> ShaderRef {
+        ShaderRef::Default
+    }
+
+    /// Whether opaque outlines sharing mesh and material are drawn with a single instanced
+    /// draw call. The vertex shader then gets the `INSTANCED` def and the model matrix of each
+    /// instance at locations 3 to 6.
+    ///
+    /// Defaults to `true` only for the default vertex shader.
+    fn instancing() -> bool {
+        matches!(Self::vertex_shader(), ShaderRef::Default)
+    }
+
+    /// Whether this material is drawn as a glow around the silhouette of the mesh instead of
+    /// an extruded hull. Glows are blended over the view from a distance field of the glowing
+    /// meshes, see [`OutlineGlowMask`](crate::OutlineGlowMask).
+    ///
+    /// The mesh is then drawn unextruded into the glow mask with the `OUTLINE_GLOW_MASK` shader def
+    /// and the fragment entry point `glow_mask`, which writes to four color targets:
+    /// the pixel coordinates of the fragment (`vec2<f32>`), the inner and outer color of the glow
+    /// (`vec4<f32>`), and its width in physical pixels and falloff exponent (`vec2<f32>`).
+    /// The default shaders have no such entry point. Defaults to `false`.
+    #[inline]
+    fn glow(&self) -> bool {
+        false
+    }
+
+    /// Returns this material's [`AlphaMode`]. Outlines with [`AlphaMode::Blend`] are alpha blended
+    /// in the `Transparent3d` phase, all others are drawn in the `Opaque3d` phase.
+    #[inline]
+    fn alpha_mode(&self) -> AlphaMode {
+        AlphaMode::Opaque
+    }
+
+    /// Applies the global [`OutlinePalette`] to the copy of this material extracted into
+    /// the render world.
+    #[allow(unused_variables)]
+    #[inline]
+    fn apply_palette(&mut self, palette: &OutlinePalette) {}
+
+    /// Fills the settings this material leaves unset from the [`OutlinePlugin`] configuration,
+    /// e.g. `OutlinePlugin::default_mode`, in the copy extracted into the render world.
+    #[allow(unused_variables)]
+    #[inline]
+    fn apply_defaults(&mut self, config: &OutlinePlugin) {}
+
+    /// Size of the uniform returned by [`Self::shared_uniform`], or `None` if materials of this
+    /// type are only prepared with [`AsBindGroup`], which is the default.
+    #[inline]
+    fn shared_uniform_size() -> Option<NonZeroU64> {
+        None
+    }
+
+    /// Packs the uniform of this material into one buffer shared by all materials of this type,
+    /// instead of preparing a buffer and bind group per material with [`AsBindGroup`], so that
+    /// animating materials does not recreate GPU resources every frame.
+    ///
+    /// The shared buffer is bound alone at binding 0 of group 2, with a dynamic offset per
+    /// material. Returns `None` by default.
+    /// See also [`OutlineSettings::shared_material_uniforms`].
+    #[inline]
+    fn shared_uniform(&self) -> Option<SharedOutlineUniform<Self>> {
+        None
+    }
+
+    /// Customizes the default [`RenderPipelineDescriptor`].
+    #[allow(unused_variables)]
+    #[inline]
+    fn specialize(
+        pipeline: &OutlinePipeline<Self>,
+        descriptor: &mut RenderPipelineDescriptor,
+        layout: &MeshVertexBufferLayout,
+        key: OutlinePipelineKey<Self>,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        Ok(())
+    }
+}
+
+/// Plugin which enables outlines drawn with the given [`OutlineMaterialTrait`] asset type.
+///
+/// `OutlinePlugin` must be added before this plugin.
+/// It already adds this plugin for `OutlineMaterial`.
+pub struct OutlineMaterialPlugin<M: OutlineMaterialTrait>(PhantomData<M>);
+
+impl<M: OutlineMaterialTrait> Default for OutlineMaterialPlugin<M> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<M: OutlineMaterialTrait> Plugin for OutlineMaterialPlugin<M>
+where
+    M::Data: PartialEq + Eq + Hash + Clone,
+{
+    fn build(&self, app: &mut App) {
+        app.add_asset::<M>()
+            .add_plugin(ExtractComponentPlugin::<Handle<M>>::default())
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                prepare_outline_mesh::<With<Handle<M>>>,
+            );
+
+        if let Ok(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app
+                .add_render_command::<Opaque3d, DrawOutlines<M>>()
+                .add_render_command::<Transparent3d, DrawOutlines<M>>()
+                .add_render_command::<OutlineGlowMask, DrawOutlines<M>>()
+                .init_resource::<OutlinePipeline<M>>()
+                .init_resource::<SpecializedMeshPipelines<OutlinePipeline<M>>>()
+                .init_resource::<ExtractedOutlineMaterials<M>>()
+                .init_resource::<RenderOutlineMaterials<M>>()
+                .init_resource::<OutlineInstanceBuffer<M>>()
+                .init_resource::<OutlineUniformBuffer<M>>()
+                .add_system_to_stage(RenderStage::Extract, extract_outline_materials::<M>)
+                .add_system_to_stage(
+                    RenderStage::Prepare,
+                    prepare_outline_materials::<M>.after(PrepareAssetLabel::PreAssetPrepare),
+                )
+                .add_system_to_stage(RenderStage::Queue, queue_outlines::<M>);
+        }
+    }
+}
+
+/// A key uniquely identifying a specialized [`OutlinePipeline`].
+pub struct OutlinePipelineKey<M: OutlineMaterialTrait> {
+    pub mesh_key: MeshPipelineKey,
+    pub bind_group_data: M::Data,
+    /// Index of the outline layer counted from the back, see `Outlines`
+    pub layer: u8,
+    /// Draw several instances with per-instance model matrices
+    pub instanced: bool,
+    /// Draw the mesh into the glow mask of the view, see [`OutlineMaterialTrait::glow`]
+    pub glow_mask: bool,
+    /// Bind the material from the shared uniform buffer, see
+    /// [`OutlineMaterialTrait::shared_uniform`]
+    pub shared_uniform: bool,
+}
+
+impl<M: OutlineMaterialTrait> Eq for OutlinePipelineKey<M> where M::Data: PartialEq {}
+
+impl<M: OutlineMaterialTrait> PartialEq for OutlinePipelineKey<M>
+where
+    M::Data: PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.mesh_key == other.mesh_key
+            && self.bind_group_data == other.bind_group_data
+            && self.layer == other.layer
+            && self.instanced == other.instanced
+            && self.glow_mask == other.glow_mask
+            && self.shared_uniform == other.shared_uniform
+    }
+}
+
+impl<M: OutlineMaterialTrait> Clone for OutlinePipelineKey<M>
+where
+    M::Data: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            mesh_key: self.mesh_key,
+            bind_group_data: self.bind_group_data.clone(),
+            layer: self.layer,
+            instanced: self.instanced,
+            glow_mask: self.glow_mask,
+            shared_uniform: self.shared_uniform,
+        }
+    }
+}
+
+impl<M: OutlineMaterialTrait> Hash for OutlinePipelineKey<M>
+where
+    M::Data: Hash,
+{
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.mesh_key.hash(state);
+        self.bind_group_data.hash(state);
+        self.layer.hash(state);
+        self.instanced.hash(state);
+        self.glow_mask.hash(state);
+        self.shared_uniform.hash(state);
+    }
+}
+
+/// Data prepared for an [`OutlineMaterialTrait`] instance.
+pub struct PreparedOutlineMaterial<M: OutlineMaterialTrait> {
+    pub bindings: Vec<OwnedBindingResource>,
+    /// `None` for a material in the shared uniform buffer
+    pub bind_group: Option<BindGroup>,
+    /// Dynamic offset of a material in the shared uniform buffer
+    pub uniform_offset: Option<u32>,
+    pub key: M::Data,
+    pub alpha_mode: AlphaMode,
+    pub glow: bool,
+}
+
+#[derive(Resource)]
+struct ExtractedOutlineMaterials<M: OutlineMaterialTrait> {
+    extracted: Vec<(Handle<M>, M)>,
+    removed: Vec<Handle<M>>,
+}
+
+impl<M: OutlineMaterialTrait> Default for ExtractedOutlineMaterials<M> {
+    fn default() -> Self {
+        Self {
+            extracted: Default::default(),
+            removed: Default::default(),
+        }
+    }
+}
+
+/// Stores all prepared representations of [`OutlineMaterialTrait`] assets for as long as they exist.
+#[derive(Resource, Deref, DerefMut)]
+pub struct RenderOutlineMaterials<M: OutlineMaterialTrait>(
+    pub HashMap<Handle<M>, PreparedOutlineMaterial<M>>,
+);
+
+impl<M: OutlineMaterialTrait> Default for RenderOutlineMaterials<M> {
+    fn default() -> Self {
+        Self(Default::default())
+    }
+}
+
+/// Extract all created or modified outline materials into the render world.
+/// All materials are extracted again when the [`OutlinePalette`] changes.
+fn extract_outline_materials<M: OutlineMaterialTrait>(
+    mut commands: Commands,
+    mut events: Extract<EventReader<AssetEvent<M>>>,
+    assets: Extract<Res<Assets<M>>>,
+    palette: Extract<Res<OutlinePalette>>,
+    config: Extract<Res<OutlinePlugin>>,
+) {
+    let mut changed_assets = HashSet::default();
+    let mut removed = Vec::new();
+    for event in events.iter() {
+        match event {
+            AssetEvent::Created { handle } | AssetEvent::Modified { handle } => {
+                changed_assets.insert(handle.clone_weak());
+            }
+            AssetEvent::Removed { handle } => {
+                changed_assets.remove(handle);
+                removed.push(handle.clone_weak());
+            }
+        }
+    }
+    if palette.is_changed() {
+        changed_assets.extend(assets.ids().map(Handle::weak));
+    }
+
+    let mut extracted = Vec::new();
+    for handle in changed_assets.drain() {
+        if let Some(asset) = assets.get(&handle) {
+            let mut asset = asset.clone();
+            asset.apply_defaults(&config);
+            asset.apply_palette(&palette);
+            extracted.push((handle, asset));
+        }
+    }
+
+    commands.insert_resource(ExtractedOutlineMaterials { extracted, removed });
+}
+
+/// Prepare the extracted outline materials for the GPU.
+/// Materials whose images are not loaded yet are retried next frame.
+/// Materials are packed into the [`OutlineUniformBuffer`] if [`OutlineMaterialTrait::shared_uniform`]
+/// supports it and [`OutlineSettings::shared_material_uniforms`] is set.
+#[allow(clippy::too_many_arguments)]
+fn prepare_outline_materials<M: OutlineMaterialTrait>(
+    mut prepare_next_frame: Local<Vec<(Handle<M>, M)>>,
+    mut extracted_assets: ResMut<ExtractedOutlineMaterials<M>>,
+    mut render_materials: ResMut<RenderOutlineMaterials<M>>,
+    mut uniforms: ResMut<OutlineUniformBuffer<M>>,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    images: Res<RenderAssets<Image>>,
+    fallback_image: Res<FallbackImage>,
+    pipeline: Res<OutlinePipeline<M>>,
+    settings: Res<OutlineSettings>,
+) {
+    let removed = std::mem::take(&mut extracted_assets.removed);
+    for handle in &removed {
+        if let Some(offset) = render_materials
+            .remove(handle)
+            .and_then(|prepared| prepared.uniform_offset)
+        {
+            uniforms.free(offset);
+        }
+    }
+
+    let queued_assets = std::mem::take(&mut *prepare_next_frame)
+        .into_iter()
+        .filter(|(handle, _)| !removed.contains(handle));
+    for (handle, material) in queued_assets.chain(std::mem::take(&mut extracted_assets.extracted)) {
+        let previous_offset = render_materials
+            .get(&handle)
+            .and_then(|prepared| prepared.uniform_offset);
+        let shared_uniform = material
+            .shared_uniform()
+            .filter(|_| settings.shared_material_uniforms);
+        if let Some(uniform) = shared_uniform {
+            let offset = previous_offset.unwrap_or_else(|| uniforms.allocate());
+            uniforms.write(offset, &uniform);
+            render_materials.insert(
+                handle,
+                PreparedOutlineMaterial {
+                    bindings: Vec::new(),
+                    bind_group: None,
+                    uniform_offset: Some(offset),
+                    key: uniform.key,
+                    alpha_mode: material.alpha_mode(),
+                    glow: material.glow(),
+                },
+            );
+            continue;
+        }
+
+        match prepare_outline_material(
+            &material,
+            &render_device,
+            &images,
+            &fallback_image,
+            &pipeline,
+        ) {
+            Ok(prepared) => {
+                if let Some(offset) = previous_offset {
+                    uniforms.free(offset);
+                }
+                render_materials.insert(handle, prepared);
+            }
+            Err(AsBindGroupError::RetryNextUpdate) => {
+                prepare_next_frame.push((handle, material));
+            }
+        }
+    }
+
+    uniforms.write_buffer(
+        &render_device,
+        &render_queue,
+        pipeline.shared_material_layout.as_ref(),
+    );
+}
+
+fn prepare_outline_material<M: OutlineMaterialTrait>(
+    material: &M,
+    render_device: &RenderDevice,
+    images: &RenderAssets<Image>,
+    fallback_image: &FallbackImage,
+    pipeline: &OutlinePipeline<M>,
+) -> Result<PreparedOutlineMaterial<M>, AsBindGroupError> {
+    let prepared = material.as_bind_group(
+        &pipeline.material_layout,
+        render_device,
+        images,
+        fallback_image,
+    )?;
+    Ok(PreparedOutlineMaterial {
+        bindings: prepared.bindings,
+        bind_group: Some(prepared.bind_group),
+        uniform_offset: None,
+        key: prepared.data,
+        alpha_mode: material.alpha_mode(),
+        glow: material.glow(),
+    })
+}
+
+/// Sets the bind group for a given [`OutlineMaterialTrait`] at the configured `I` index.
+pub struct SetOutlineMaterialBindGroup<M: OutlineMaterialTrait, const I: usize>(PhantomData<M>);
+impl<M: OutlineMaterialTrait, const I: usize> EntityRenderCommand
+    for SetOutlineMaterialBindGroup<M, I>
+{
+    type Param = (
+        SRes<RenderOutlineMaterials<M>>,
+        SRes<OutlineUniformBuffer<M>>,
+        SQuery<Read<Handle<M>>>,
+        SRes<OutlineWarnings>,
+    );
+    fn render<'w>(
+        _view: Entity,
+        item: Entity,
+        (materials, uniforms, query, warnings): SystemParamItem<'w, '_, Self::Param>,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        let material_handle = match query.get(item) {
+            Ok(material_handle) => material_handle,
+            Err(_) => {
+                warnings.warn_once(item, "Outline material handle is missing");
+                return RenderCommandResult::Failure;
+            }
+        };
+        let material = match materials.into_inner().get(material_handle) {
+            Some(material) => material,
+            None => {
+                warnings.warn_once(item, "Outline material is not prepared");
+                return RenderCommandResult::Failure;
+            }
+        };
+        let (bind_group, dynamic_offsets): (_, &[u32]) = match &material.uniform_offset {
+            Some(offset) => (
+                uniforms.into_inner().bind_group(),
+                std::slice::from_ref(offset),
+            ),
+            None => (material.bind_group.as_ref(), &[]),
+        };
+        match bind_group {
+            Some(bind_group) => pass.set_bind_group(I, bind_group, dynamic_offsets),
+            None => {
+                warnings.warn_once(item, "Outline uniform buffer is not written");
+                return RenderCommandResult::Failure;
+            }
+        }
+        RenderCommandResult::Success
+    }
+}
@@ -0,0 +1,109 @@
+use bevy::{
+    math::{Mat3, Vec3},
+    prelude::{Color, FromReflect, Reflect, ReflectResource, Resource},
+};
+
+/// Global remapping of outline colors, e.g. for accessibility options.
+/// Outline widths are scaled by `OutlineSettings::width_scale` instead.
+///
+/// It is applied when outline materials are extracted into the render world,
+/// so the assets themselves (including the highlights of the `picking` feature) stay untouched.
+/// Custom outline materials opt in through `OutlineMaterialTrait::apply_palette`.
+#[derive(Resource, Clone, Debug, Default, Reflect)]
+#[reflect(Resource)]
+pub struct OutlinePalette {
+    pub remap: PaletteRemap,
+}
+
+/// Color remapping of [`OutlinePalette`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Reflect, FromReflect)]
+pub enum PaletteRemap {
+    /// Keep the colors as they are
+    #[default]
+    None,
+    /// Shift colors to stay distinguishable without red cones
+    Protanopia,
+    /// Shift colors to stay distinguishable without green cones
+    Deuteranopia,
+    /// Shift colors to stay distinguishable without blue cones
+    Tritanopia,
+    /// Fully saturate colors, and snap grays to black or white
+    HighContrast,
+}
+
+impl OutlinePalette {
+    /// Remap a color according to [`Self::remap`]. Alpha is kept.
+    pub fn remap_color(&self, color: Color) -> Color {
+        match self.remap {
+            PaletteRemap::None => color,
+            PaletteRemap::Protanopia => daltonize(color, PROTANOPIA),
+            PaletteRemap::Deuteranopia => daltonize(color, DEUTERANOPIA),
+            PaletteRemap::Tritanopia => daltonize(color, TRITANOPIA),
+            PaletteRemap::HighContrast => high_contrast(color),
+        }
+    }
+}
+
+// The matrices below are written row by row and transposed into glam's column-major layout.
+const RGB_TO_LMS: [f32; 9] = [
+    17.8824, 43.5161, 4.11935, //
+    3.45565, 27.1554, 3.86714, //
+    0.0299566, 0.184309, 1.46709,
+];
+const PROTANOPIA: [f32; 9] = [
+    0., 2.02344, -2.52581, //
+    0., 1., 0., //
+    0., 0., 1.,
+];
+const DEUTERANOPIA: [f32; 9] = [
+    1., 0., 0., //
+    0.494207, 0., 1.24827, //
+    0., 0., 1.,
+];
+const TRITANOPIA: [f32; 9] = [
+    1., 0., 0., //
+    0., 1., 0., //
+    -0.395913, 0.801109, 0.,
+];
+// Move the information lost by the deficiency into the channels which are still perceived
+const ERROR_SHIFT: [f32; 9] = [
+    0., 0., 0., //
+    0.7, 1., 0., //
+    0.7, 0., 1.,
+];
+
+fn rows(m: [f32; 9]) -> Mat3 {
+    Mat3::from_cols_array(&m).transpose()
+}
+
+/// Daltonization: simulate the color deficiency and redistribute the difference.
+/// The matrices are defined for linear RGB.
+fn daltonize(color: Color, simulation: [f32; 9]) -> Color {
+    let [r, g, b, a] = color.as_linear_rgba_f32();
+    let rgb = Vec3::new(r, g, b);
+
+    let rgb_to_lms = rows(RGB_TO_LMS);
+    let simulated = rgb_to_lms.inverse() * rows(simulation) * rgb_to_lms * rgb;
+    let corrected = (rgb + rows(ERROR_SHIFT) * (rgb - simulated)).clamp(Vec3::ZERO, Vec3::ONE);
+
+    Color::rgba_linear(corrected.x, corrected.y, corrected.z, a)
+}
+
+fn high_contrast(color: Color) -> Color {
+    match color.as_hsla() {
+        Color::Hsla {
+            hue,
+            saturation,
+            lightness,
+            alpha,
+        } => {
+            if saturation < 0.1 {
+                let value = if lightness < 0.5 { 0. } else { 1. };
+                Color::rgba(value, value, value, alpha)
+            } else {
+                Color::hsla(hue, 1., 0.5, alpha)
+            }
+        }
+        _ => unreachable!(),
+    }
+}
@@ -1,11 +1,9 @@
-use std::ops::Deref;
-
 use bevy::{
     app::PluginGroupBuilder,
-    ecs::{schedule::ShouldRun, system::EntityCommands},
+    ecs::schedule::ShouldRun,
     prelude::{
-        App, Changed, Commands, CoreStage, Deref, Entity, Handle, Or, Plugin, PluginGroup, Query,
-        Res, SystemSet,
+        App, Changed, Commands, CoreStage, Entity, IntoSystemDescriptor, Or, Plugin, PluginGroup,
+        Query, Res, SystemSet,
     },
     ui::Interaction,
 };
@@ -15,7 +13,15 @@ use bevy_mod_picking::{
     PickingSystem, Selection,
 };
 
-use crate::{OutlineMaterial, OutlinePlugin};
+pub use crate::highlight::{
+    AddPickingStateMarker, BaseOutline, HoverOutline, MarkerStates, PickingDisabled,
+    PickingDragTarget, PickingFocused, PickingOutlineStates, PickingOutlines, PickingSelected,
+    PickingState, PickingStateMarker, PressedOutline, SelectedOutline, StateOutline,
+};
+use crate::{
+    highlight::{add_picking_backend, set_highlight, HighlightOutlines, OutlineState},
+    OutlinePlugin,
+};
 
 /// Alternative to the `bevy_mod_picking`'s `DefaultPickingPlugins`.
 /// Object get outlined instead of changing materials when hovered, clicked or selected.
@@ -24,31 +30,17 @@ impl PluginGroup for DefaultPickingPlugins {
     fn build(&mut self, group: &mut PluginGroupBuilder) {
         group.add(PickingPlugin);
         group.add(InteractablePickingPlugin);
-        group.add(OutlinePlugin);
+        group.add(OutlinePlugin::default());
         group.add(OutlinePickingPlugin);
     }
 }
 
-/// `OutlineMaterial` handle resource used when object is hovered.
-/// If this resource does not exist in world, no outline will show.
-#[derive(Deref)]
-pub struct HoverOutline(pub Handle<OutlineMaterial>);
-
-/// `OutlineMaterial` handle resource used when object is selected.
-/// If this resource does not exist in world, no outline will show.
-#[derive(Deref)]
-pub struct SelectedOutline(pub Handle<OutlineMaterial>);
-
-/// `OutlineMaterial` handle resource used when object is pressed or clicked.
-/// If this resource does not exist in world, no outline will show.
-#[derive(Deref)]
-pub struct PressedOutline(pub Handle<OutlineMaterial>);
-
 /// Outline picking plugin as an alternative to `HighlightablePickingPlugin` in `bevy_mod_picking`
 pub struct OutlinePickingPlugin;
 
 impl Plugin for OutlinePickingPlugin {
     fn build(&self, app: &mut App) {
+        add_picking_backend::<Interaction>(app);
         app.add_system_set_to_stage(
             CoreStage::PreUpdate,
             SystemSet::new()
@@ -73,59 +65,44 @@ impl Plugin for OutlinePickingPlugin {
 fn mesh_highlighting(
     paused: Option<Res<PausedForBlockers>>,
     mut commands: Commands,
-    hover_outline: Option<Res<HoverOutline>>,
-    pressed_outline: Option<Res<PressedOutline>>,
-    selected_outline: Option<Res<SelectedOutline>>,
+    outlines: HighlightOutlines,
     mut interaction_query: Query<
-        (Entity, &Interaction, Option<&Selection>),
-        Or<(Changed<Interaction>, Changed<Selection>)>,
+        (
+            Entity,
+            &Interaction,
+            Option<&Selection>,
+            Option<&MarkerStates>,
+            Option<&PickingOutlines>,
+            OutlineState,
+        ),
+        Or<(
+            Changed<Interaction>,
+            Changed<Selection>,
+            Changed<MarkerStates>,
+            Changed<PickingOutlines>,
+        )>,
     >,
 ) {
-    if let Some(paused) = paused {
-        if paused.is_paused() {
-            for (ent, _, selection) in interaction_query.iter_mut() {
-                if let Some(selection) = selection {
-                    let mut entity_commands = commands.entity(ent);
-                    if selection.selected() {
-                        set_outline(&mut entity_commands, &selected_outline);
-                        continue;
-                    }
-                    entity_commands.remove::<Handle<OutlineMaterial>>();
-                }
+    // Only selections and markers are shown while picking is paused
+    let paused = paused.map_or(false, |paused| paused.is_paused());
+
+    for (ent, interaction, selection, markers, overrides, state) in interaction_query.iter_mut() {
+        let mut active = Vec::new();
+        if !paused {
+            match *interaction {
+                Interaction::Clicked => active.push(PickingState::Pressed),
+                Interaction::Hovered => active.push(PickingState::Hovered),
+                Interaction::None => {}
             }
-            return;
         }
-    }
-    for (ent, interaction, selection) in interaction_query.iter_mut() {
-        let mut entity_commands = commands.entity(ent);
-        match *interaction {
-            Interaction::Clicked => {
-                set_outline(&mut entity_commands, &pressed_outline);
-            }
-            Interaction::Hovered => {
-                set_outline(&mut entity_commands, &hover_outline);
-            }
-            Interaction::None => {
-                if let Some(selection) = selection {
-                    if selection.selected() {
-                        set_outline(&mut entity_commands, &selected_outline);
-                        continue;
-                    }
-                }
-                entity_commands.remove::<Handle<OutlineMaterial>>();
-            }
-        };
-    }
-}
+        if selection.map_or(false, Selection::selected) {
+            active.push(PickingState::Selected);
+        }
+        if let Some(markers) = markers {
+            active.extend(markers.iter().copied());
+        }
 
-#[inline]
-fn set_outline<T: Deref<Target = Handle<OutlineMaterial>> + Send + Sync + 'static>(
-    entity_commands: &mut EntityCommands,
-    outline: &Option<Res<T>>,
-) {
-    if let Some(outline) = outline.as_ref() {
-        entity_commands.insert((*outline).clone());
-    } else {
-        entity_commands.remove::<Handle<OutlineMaterial>>();
+        let outline = outlines.outline(active, overrides);
+        set_highlight(&mut commands.entity(ent), outline, &state);
     }
 }
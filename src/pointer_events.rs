@@ -0,0 +1,158 @@
+//! Outline highlighting driven by pointer events, for any picking backend.
+//!
+//! This is a generic adapter, not an integration with a specific picking crate: the picking
+//! backend of the app sends [`OutlinePointerEvent`]s, e.g. forwarded from its own events:
+//!
+//! ```ignore
+//! fn forward_over(mut over: EventReader<PointerEvent<Over>>, mut outline: EventWriter<OutlinePointerEvent>) {
+//!     outline.send_batch(over.iter().map(|event| OutlinePointerEvent::Over(event.target)));
+//! }
+//! ```
+
+use bevy::{
+    prelude::{
+        App, Changed, Commands, Component, CoreStage, Entity, EventReader, Or, Plugin, Query,
+    },
+    utils::HashMap,
+};
+
+use crate::highlight::{
+    add_picking_backend, set_highlight, HighlightOutlines, MarkerStates, OutlineState,
+    PickingOutlines, PickingState,
+};
+
+/// Pointer events sent by the picking backend of the app, targeting the picked entity
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutlinePointerEvent {
+    /// The pointer entered the entity
+    Over(Entity),
+    /// The pointer left the entity
+    Out(Entity),
+    /// A pointer button was pressed on the entity
+    Down(Entity),
+    /// A pointer button was released on the entity
+    Up(Entity),
+    /// The entity was selected
+    Select(Entity),
+    /// The entity was deselected
+    Deselect(Entity),
+}
+
+impl OutlinePointerEvent {
+    pub fn target(&self) -> Entity {
+        match *self {
+            Self::Over(entity)
+            | Self::Out(entity)
+            | Self::Down(entity)
+            | Self::Up(entity)
+            | Self::Select(entity)
+            | Self::Deselect(entity) => entity,
+        }
+    }
+}
+
+/// Pointer states of an entity, updated from [`OutlinePointerEvent`]s
+#[derive(Component, Clone, Copy, Default, Debug)]
+pub struct PointerStates {
+    pub hovered: bool,
+    pub pressed: bool,
+    pub selected: bool,
+}
+
+impl PointerStates {
+    fn apply(&mut self, event: &OutlinePointerEvent) {
+        match event {
+            OutlinePointerEvent::Over(_) => self.hovered = true,
+            OutlinePointerEvent::Out(_) => {
+                self.hovered = false;
+                // Releasing the button elsewhere emits no `Up` on this entity
+                self.pressed = false;
+            }
+            OutlinePointerEvent::Down(_) => self.pressed = true,
+            OutlinePointerEvent::Up(_) => self.pressed = false,
+            OutlinePointerEvent::Select(_) => self.selected = true,
+            OutlinePointerEvent::Deselect(_) => self.selected = false,
+        }
+    }
+}
+
+/// Outlines entities from [`OutlinePointerEvent`]s, the event-based counterpart of
+/// `picking::OutlinePickingPlugin`.
+///
+/// Outlines are configured with the same resources and components:
+/// [`HoverOutline`](crate::highlight::HoverOutline), [`PickingOutlines`],
+/// [`PickingOutlineStates`](crate::highlight::PickingOutlineStates) and the state markers.
+pub struct OutlinePointerEventsPlugin;
+
+impl Plugin for OutlinePointerEventsPlugin {
+    fn build(&self, app: &mut App) {
+        add_picking_backend::<PointerStates>(app);
+        app.add_event::<OutlinePointerEvent>()
+            .add_system_to_stage(CoreStage::PostUpdate, pointer_highlighting);
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn pointer_highlighting(
+    mut commands: Commands,
+    mut events: EventReader<OutlinePointerEvent>,
+    outlines: HighlightOutlines,
+    changed: Query<Entity, Or<(Changed<MarkerStates>, Changed<PickingOutlines>)>>,
+    mut query: Query<(
+        Option<&mut PointerStates>,
+        Option<&MarkerStates>,
+        Option<&PickingOutlines>,
+        OutlineState,
+    )>,
+) {
+    let mut pointer_states: HashMap<Entity, PointerStates> = HashMap::default();
+    for event in events.iter() {
+        let entity = event.target();
+        let states = pointer_states.entry(entity).or_insert_with(|| {
+            query
+                .get(entity)
+                .ok()
+                .and_then(|(states, ..)| states.map(|states| *states))
+                .unwrap_or_default()
+        });
+        states.apply(event);
+    }
+    for entity in changed.iter() {
+        if let Ok((states, ..)) = query.get(entity) {
+            pointer_states
+                .entry(entity)
+                .or_insert_with(|| states.map(|states| *states).unwrap_or_default());
+        }
+    }
+
+    for (entity, pointer) in pointer_states {
+        // The target may have been despawned since the event was sent
+        let (states, markers, overrides, state) = match query.get_mut(entity) {
+            Ok(components) => components,
+            Err(_) => continue,
+        };
+        match states {
+            Some(mut states) => *states = pointer,
+            None => {
+                commands.entity(entity).insert(pointer);
+            }
+        }
+
+        let mut active = Vec::new();
+        if pointer.pressed {
+            active.push(PickingState::Pressed);
+        }
+        if pointer.hovered {
+            active.push(PickingState::Hovered);
+        }
+        if pointer.selected {
+            active.push(PickingState::Selected);
+        }
+        if let Some(markers) = markers {
+            active.extend(markers.iter().copied());
+        }
+
+        let outline = outlines.outline(active, overrides);
+        set_highlight(&mut commands.entity(entity), outline, &state);
+    }
+}
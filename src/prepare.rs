@@ -1,17 +1,30 @@
 use bevy::{
-    prelude::{Assets, Component, Handle, Mesh, Query, ResMut, With},
+    ecs::query::ReadOnlyWorldQuery,
+    prelude::{Assets, Component, Handle, Mesh, Query, Res, ResMut},
     render::mesh::VertexAttributeValues,
+    utils::Instant,
 };
 
-use crate::{smooth_normal::smooth_normal, OutlineMaterial, ATTRIBUTE_OUTLINE_NORMAL};
+use crate::{
+    diagnostics::OutlineStats, smooth_normal::smooth_normal, OutlinePlugin, OutlineSettings,
+    ATTRIBUTE_OUTLINE_NORMAL,
+};
 
 #[derive(Component, Clone)]
 pub struct OutlineNormals(pub VertexAttributeValues);
 
-pub fn prepare_outline_mesh(
+/// Compute the outline normals of the meshes of entities matching the filter `F`
+pub fn prepare_outline_mesh<F: ReadOnlyWorldQuery + 'static>(
+    config: Res<OutlinePlugin>,
+    settings: Res<OutlineSettings>,
+    stats: Option<Res<OutlineStats>>,
     mut meshes: ResMut<Assets<Mesh>>,
-    outline_without_normals: Query<&Handle<Mesh>, With<Handle<OutlineMaterial>>>,
+    outline_without_normals: Query<&Handle<Mesh>, F>,
 ) {
+    if !config.auto_generate_normals || !settings.enabled {
+        return;
+    }
+    let start = Instant::now();
     for mesh_handle in outline_without_normals.iter() {
         if let Some(mesh) = meshes.get_mut(mesh_handle) {
             // Don't have outline normal, just compute it.
@@ -20,4 +33,7 @@ pub fn prepare_outline_mesh(
             }
         }
     }
+    if let Some(stats) = stats {
+        stats.record_prepare_mesh_time(start.elapsed());
+    }
 }
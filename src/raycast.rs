@@ -0,0 +1,538 @@
+//! Lightweight CPU picking of meshes, for apps without `bevy_mod_picking`.
+
+use bevy::{
+    input::InputSystem,
+    math::{Mat4, Ray, Vec2, Vec3},
+    prelude::{
+        App, Assets, Camera, Changed, Commands, Component, ComputedVisibility, CoreStage, Entity,
+        EventWriter, GlobalTransform, Handle, Input, IntoSystemDescriptor, Mesh, MouseButton, Or,
+        Plugin, Query, Res, ResMut, Resource, SystemLabel, With,
+    },
+    render::{
+        mesh::{PrimitiveTopology, VertexAttributeValues},
+        primitives::Aabb,
+    },
+    utils::HashSet,
+    window::Windows,
+};
+
+use crate::highlight::{
+    active_camera, add_picking_backend, set_highlight, HighlightOutlines, MarkerStates,
+    OutlineState, PickingOutlines, PickingState,
+};
+
+/// Picks the nearest [`RaycastPickable`] mesh under the [`RaycastCursor`] by casting a ray
+/// against its AABB, then its triangles. Picked entities are outlined like with
+/// `picking::OutlinePickingPlugin`, and [`RaycastPickingEvent`]s are sent.
+pub struct OutlineRaycastPickingPlugin {
+    /// Update [`RaycastCursor`] from the primary window and the left mouse button.
+    /// Disable it to drive picking with synthetic input, e.g. in headless tests.
+    pub window_cursor: bool,
+}
+
+impl Default for OutlineRaycastPickingPlugin {
+    fn default() -> Self {
+        Self {
+            window_cursor: true,
+        }
+    }
+}
+
+impl Plugin for OutlineRaycastPickingPlugin {
+    fn build(&self, app: &mut App) {
+        add_picking_backend::<RaycastPickable>(app);
+        app.init_resource::<RaycastCursor>()
+            .init_resource::<RaycastPickingState>()
+            .add_event::<RaycastPickingEvent>()
+            .add_system_to_stage(
+                CoreStage::PreUpdate,
+                raycast_picking
+                    .label(RaycastPickingSystem::Picking)
+                    .after(RaycastPickingSystem::Cursor),
+            );
+        if self.window_cursor {
+            app.add_system_to_stage(
+                CoreStage::PreUpdate,
+                window_cursor
+                    .label(RaycastPickingSystem::Cursor)
+                    .after(InputSystem),
+            );
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, SystemLabel)]
+pub enum RaycastPickingSystem {
+    /// Updates [`RaycastCursor`] from the window
+    Cursor,
+    /// Picks entities and updates their outlines
+    Picking,
+}
+
+/// Marks entities with a `Handle<Mesh>` which can be picked by [`OutlineRaycastPickingPlugin`]
+#[derive(Component, Default)]
+pub struct RaycastPickable;
+
+/// Cursor used for picking
+#[derive(Resource, Clone, Debug, Default)]
+pub struct RaycastCursor {
+    /// Position in logical pixels from the bottom-left corner of the viewport,
+    /// `None` if the cursor is outside of the window
+    pub position: Option<Vec2>,
+    /// Whether the pick button is held down
+    pub pressed: bool,
+}
+
+/// Events sent by [`OutlineRaycastPickingPlugin`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RaycastPickingEvent {
+    /// The cursor entered the entity
+    Over(Entity),
+    /// The cursor left the entity
+    Out(Entity),
+    /// The button was pressed on the entity
+    Down(Entity),
+    /// The button was released after being pressed on the entity
+    Up(Entity),
+    /// The button was pressed and released on the entity
+    Click(Entity),
+}
+
+/// Currently hovered and pressed entities
+#[derive(Resource, Default, Debug)]
+pub struct RaycastPickingState {
+    hovered: Option<Entity>,
+    pressed: Option<Entity>,
+    button: bool,
+}
+
+impl RaycastPickingState {
+    pub fn hovered(&self) -> Option<Entity> {
+        self.hovered
+    }
+
+    pub fn pressed(&self) -> Option<Entity> {
+        self.pressed
+    }
+}
+
+fn window_cursor(
+    windows: Option<Res<Windows>>,
+    mouse: Option<Res<Input<MouseButton>>>,
+    mut cursor: ResMut<RaycastCursor>,
+) {
+    cursor.position = windows
+        .as_ref()
+        .and_then(|windows| windows.get_primary())
+        .and_then(|window| window.cursor_position());
+    cursor.pressed = mouse.map_or(false, |mouse| mouse.pressed(MouseButton::Left));
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn raycast_picking(
+    mut commands: Commands,
+    cursor: Res<RaycastCursor>,
+    mut picking: ResMut<RaycastPickingState>,
+    mut events: EventWriter<RaycastPickingEvent>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    meshes: Res<Assets<Mesh>>,
+    pickables: Query<
+        (
+            Entity,
+            &Handle<Mesh>,
+            &GlobalTransform,
+            Option<&Aabb>,
+            Option<&ComputedVisibility>,
+        ),
+        With<RaycastPickable>,
+    >,
+    outlines: HighlightOutlines,
+    changed: Query<
+        Entity,
+        (
+            With<RaycastPickable>,
+            Or<(Changed<MarkerStates>, Changed<PickingOutlines>)>,
+        ),
+    >,
+    query: Query<
+        (
+            Option<&MarkerStates>,
+            Option<&PickingOutlines>,
+            OutlineState,
+        ),
+        With<RaycastPickable>,
+    >,
+) {
+    // Rays of the active camera with the highest priority
+    let ray = cursor.position.and_then(|position| {
+        active_camera(cameras.iter())
+            .and_then(|(camera, transform)| camera.viewport_to_world(transform, position))
+    });
+    let hovered = ray.and_then(|ray| {
+        pickables
+            .iter()
+            .filter(|(.., visibility)| {
+                visibility.map_or(true, ComputedVisibility::is_visible_in_hierarchy)
+            })
+            .filter_map(|(entity, mesh, transform, aabb, _)| {
+                let mesh = meshes.get(mesh)?;
+                ray_mesh_distance(ray, mesh, transform.compute_matrix(), aabb)
+                    .map(|distance| (entity, distance))
+            })
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(entity, _)| entity)
+    });
+
+    let mut dirty: HashSet<Entity> = changed.iter().collect();
+    let previous_hovered = picking.hovered;
+    let previous_pressed = picking.pressed;
+    if hovered != previous_hovered {
+        if let Some(entity) = previous_hovered {
+            events.send(RaycastPickingEvent::Out(entity));
+        }
+        if let Some(entity) = hovered {
+            events.send(RaycastPickingEvent::Over(entity));
+        }
+        picking.hovered = hovered;
+    }
+    if cursor.pressed && !picking.button {
+        picking.pressed = hovered;
+        if let Some(entity) = hovered {
+            events.send(RaycastPickingEvent::Down(entity));
+        }
+    } else if !cursor.pressed && picking.button {
+        if let Some(entity) = picking.pressed.take() {
+            events.send(RaycastPickingEvent::Up(entity));
+            if hovered == Some(entity) {
+                events.send(RaycastPickingEvent::Click(entity));
+            }
+        }
+    }
+    picking.button = cursor.pressed;
+    if picking.hovered != previous_hovered || picking.pressed != previous_pressed {
+        dirty.extend(
+            [
+                previous_hovered,
+                previous_pressed,
+                picking.hovered,
+                picking.pressed,
+            ]
+            .into_iter()
+            .flatten(),
+        );
+    }
+
+    for entity in dirty {
+        // The entity may have been despawned or lost `RaycastPickable`
+        let (markers, overrides, state) = match query.get(entity) {
+            Ok(components) => components,
+            Err(_) => continue,
+        };
+        let mut active = Vec::new();
+        if picking.hovered == Some(entity) {
+            // Pressed entities are shown as pressed only while hovered
+            if picking.pressed == Some(entity) {
+                active.push(PickingState::Pressed);
+            }
+            active.push(PickingState::Hovered);
+        }
+        if let Some(markers) = markers {
+            active.extend(markers.iter().copied());
+        }
+
+        let outline = outlines.outline(active, overrides);
+        set_highlight(&mut commands.entity(entity), outline, &state);
+    }
+}
+
+/// Distance along `ray` to the nearest triangle of `mesh`, tested against the AABB first
+fn ray_mesh_distance(ray: Ray, mesh: &Mesh, model: Mat4, aabb: Option<&Aabb>) -> Option<f32> {
+    if mesh.primitive_topology() != PrimitiveTopology::TriangleList {
+        return None;
+    }
+    let positions = match mesh.attribute(Mesh::ATTRIBUTE_POSITION) {
+        Some(VertexAttributeValues::Float32x3(positions)) => positions,
+        _ => return None,
+    };
+
+    // In model space the distance is the same as long as the direction is not normalized
+    let world_to_model = model.inverse();
+    let ray = Ray {
+        origin: world_to_model.transform_point3(ray.origin),
+        direction: world_to_model.transform_vector3(ray.direction),
+    };
+    let aabb = match aabb {
+        Some(aabb) => aabb.clone(),
+        None => mesh.compute_aabb()?,
+    };
+    ray_aabb_distance(ray, &aabb)?;
+
+    let triangles: Vec<[usize; 3]> = match mesh.indices() {
+        Some(indices) => {
+            let indices: Vec<usize> = indices.iter().collect();
+            indices
+                .chunks_exact(3)
+                .map(|triangle| [triangle[0], triangle[1], triangle[2]])
+                .collect()
+        }
+        None => (0..positions.len() / 3)
+            .map(|i| [3 * i, 3 * i + 1, 3 * i + 2])
+            .collect(),
+    };
+    triangles
+        .into_iter()
+        .filter_map(|triangle| {
+            let [a, b, c] = triangle.map(|index| positions.get(index).copied().map(Vec3::from));
+            ray_triangle_distance(ray, [a?, b?, c?])
+        })
+        .min_by(f32::total_cmp)
+}
+
+/// Slab test, returns the distance at which the ray enters the box
+fn ray_aabb_distance(ray: Ray, aabb: &Aabb) -> Option<f32> {
+    let min = Vec3::from(aabb.min());
+    let max = Vec3::from(aabb.max());
+    let inverse = ray.direction.recip();
+    let t1 = (min - ray.origin) * inverse;
+    let t2 = (max - ray.origin) * inverse;
+    let near = t1.min(t2).max_element();
+    let far = t1.max(t2).min_element();
+    (far >= near.max(0.)).then_some(near.max(0.))
+}
+
+/// Möller–Trumbore intersection of a ray and a two-sided triangle
+fn ray_triangle_distance(ray: Ray, [a, b, c]: [Vec3; 3]) -> Option<f32> {
+    let edge1 = b - a;
+    let edge2 = c - a;
+    let p = ray.direction.cross(edge2);
+    let determinant = edge1.dot(p);
+    if determinant.abs() < f32::EPSILON {
+        return None;
+    }
+    let inverse = determinant.recip();
+    let s = ray.origin - a;
+    let u = s.dot(p) * inverse;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let q = s.cross(edge1);
+    let v = ray.direction.dot(q) * inverse;
+    if v < 0. || u + v > 1. {
+        return None;
+    }
+    let distance = edge2.dot(q) * inverse;
+    (distance >= 0.).then_some(distance)
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{
+        asset::AssetPlugin,
+        ecs::event::{Events, ManualEventReader},
+        prelude::{
+            default, shape, AddAsset, Image, MinimalPlugins, PerspectiveProjection, Transform,
+        },
+        render::{
+            camera::{CameraProjectionPlugin, RenderTarget},
+            render_resource::{Extent3d, TextureDimension, TextureFormat},
+        },
+        window::WindowPlugin,
+    };
+
+    use super::*;
+    use crate::{highlight::HoverOutline, OutlineMaterial};
+
+    /// Headless app with a unit cube in front of a camera rendering to a 100x100 image
+    fn setup() -> (App, Entity) {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(AssetPlugin::default())
+            .add_plugin(WindowPlugin {
+                add_primary_window: false,
+                exit_on_all_closed: false,
+                ..default()
+            })
+            .add_asset::<Mesh>()
+            .add_asset::<Image>()
+            .add_plugin(CameraProjectionPlugin::<PerspectiveProjection>::default())
+            .add_plugin(OutlineRaycastPickingPlugin {
+                window_cursor: false,
+            })
+            .insert_resource(HoverOutline(Handle::default()));
+
+        let image = app
+            .world
+            .resource_mut::<Assets<Image>>()
+            .add(Image::new_fill(
+                Extent3d {
+                    width: 100,
+                    height: 100,
+                    depth_or_array_layers: 1,
+                },
+                TextureDimension::D2,
+                &[0, 0, 0, 255],
+                TextureFormat::Rgba8UnormSrgb,
+            ));
+        let mesh = app
+            .world
+            .resource_mut::<Assets<Mesh>>()
+            .add(Mesh::from(shape::Cube { size: 1. }));
+
+        app.world.spawn((
+            Camera {
+                target: RenderTarget::Image(image),
+                ..default()
+            },
+            PerspectiveProjection::default(),
+            GlobalTransform::from(Transform::from_xyz(0., 0., 5.).looking_at(Vec3::ZERO, Vec3::Y)),
+        ));
+        let cube = app
+            .world
+            .spawn((mesh, GlobalTransform::IDENTITY, RaycastPickable))
+            .id();
+
+        // Let the camera system compute the viewport of the image target
+        app.update();
+        (app, cube)
+    }
+
+    fn update(
+        app: &mut App,
+        reader: &mut ManualEventReader<RaycastPickingEvent>,
+    ) -> Vec<RaycastPickingEvent> {
+        app.update();
+        let events = app.world.resource::<Events<RaycastPickingEvent>>();
+        reader.iter(events).copied().collect()
+    }
+
+    #[test]
+    fn hover_and_click() {
+        let (mut app, cube) = setup();
+        let mut reader = app
+            .world
+            .resource::<Events<RaycastPickingEvent>>()
+            .get_reader();
+
+        app.world.resource_mut::<RaycastCursor>().position = Some(Vec2::new(50., 50.));
+        assert_eq!(
+            update(&mut app, &mut reader),
+            [RaycastPickingEvent::Over(cube)]
+        );
+        assert_eq!(
+            app.world.resource::<RaycastPickingState>().hovered(),
+            Some(cube)
+        );
+        assert!(app.world.get::<Handle<OutlineMaterial>>(cube).is_some());
+
+        app.world.resource_mut::<RaycastCursor>().pressed = true;
+        assert_eq!(
+            update(&mut app, &mut reader),
+            [RaycastPickingEvent::Down(cube)]
+        );
+
+        app.world.resource_mut::<RaycastCursor>().pressed = false;
+        assert_eq!(
+            update(&mut app, &mut reader),
+            [
+                RaycastPickingEvent::Up(cube),
+                RaycastPickingEvent::Click(cube)
+            ]
+        );
+
+        // The corner of the image target is beside the cube
+        app.world.resource_mut::<RaycastCursor>().position = Some(Vec2::new(1., 1.));
+        assert_eq!(
+            update(&mut app, &mut reader),
+            [RaycastPickingEvent::Out(cube)]
+        );
+        assert!(app.world.get::<Handle<OutlineMaterial>>(cube).is_none());
+    }
+
+    #[test]
+    fn release_beside_the_entity_is_no_click() {
+        let (mut app, cube) = setup();
+        let mut reader = app
+            .world
+            .resource::<Events<RaycastPickingEvent>>()
+            .get_reader();
+
+        let mut cursor = app.world.resource_mut::<RaycastCursor>();
+        cursor.position = Some(Vec2::new(50., 50.));
+        cursor.pressed = true;
+        assert_eq!(
+            update(&mut app, &mut reader),
+            [
+                RaycastPickingEvent::Over(cube),
+                RaycastPickingEvent::Down(cube)
+            ]
+        );
+
+        let mut cursor = app.world.resource_mut::<RaycastCursor>();
+        cursor.position = Some(Vec2::new(1., 1.));
+        cursor.pressed = false;
+        assert_eq!(
+            update(&mut app, &mut reader),
+            [
+                RaycastPickingEvent::Out(cube),
+                RaycastPickingEvent::Up(cube)
+            ]
+        );
+    }
+
+    fn ray(origin: Vec3, direction: Vec3) -> Ray {
+        Ray { origin, direction }
+    }
+
+    #[test]
+    fn ray_triangle() {
+        let triangle = [Vec3::ZERO, Vec3::X, Vec3::Y];
+        assert_eq!(
+            ray_triangle_distance(ray(Vec3::new(0.25, 0.25, 2.), -Vec3::Z), triangle),
+            Some(2.)
+        );
+        // Triangles are two-sided
+        assert_eq!(
+            ray_triangle_distance(ray(Vec3::new(0.25, 0.25, -1.), Vec3::Z), triangle),
+            Some(1.)
+        );
+        // Beside the triangle
+        assert_eq!(
+            ray_triangle_distance(ray(Vec3::new(0.75, 0.75, 1.), -Vec3::Z), triangle),
+            None
+        );
+        // Behind the origin
+        assert_eq!(
+            ray_triangle_distance(ray(Vec3::new(0.25, 0.25, 1.), Vec3::Z), triangle),
+            None
+        );
+        // Parallel to the triangle
+        assert_eq!(
+            ray_triangle_distance(ray(Vec3::new(-1., 0.25, 0.), Vec3::X), triangle),
+            None
+        );
+    }
+
+    #[test]
+    fn ray_aabb() {
+        let aabb = Aabb::from_min_max(Vec3::splat(-1.), Vec3::splat(1.));
+        assert_eq!(
+            ray_aabb_distance(ray(Vec3::new(0.5, 0.5, 5.), -Vec3::Z), &aabb),
+            Some(4.)
+        );
+        // Starting inside the box
+        assert_eq!(
+            ray_aabb_distance(ray(Vec3::new(0.5, 0.5, 0.5), Vec3::X), &aabb),
+            Some(0.)
+        );
+        // Beside the box
+        assert_eq!(
+            ray_aabb_distance(ray(Vec3::new(2., 0.5, 5.), -Vec3::Z), &aabb),
+            None
+        );
+        // Pointing away from the box
+        assert_eq!(
+            ray_aabb_distance(ray(Vec3::new(0.5, 0.5, 5.), Vec3::Z), &aabb),
+            None
+        );
+    }
+}
@@ -0,0 +1,31 @@
+#import bevy_core_pipeline::fullscreen_vertex_shader
+
+// Pixel coordinates of the nearest glowing pixel, negative where there is none
+@group(0) @binding(0)
+var seeds: texture_2d<f32>;
+// The following textures are read at the nearest glowing pixel
+@group(0) @binding(1)
+var inner_colors: texture_2d<f32>;
+@group(0) @binding(2)
+var outer_colors: texture_2d<f32>;
+// Width of the glow in physical pixels and its falloff
+@group(0) @binding(3)
+var params: texture_2d<f32>;
+
+@fragment
+fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    let seed = textureLoad(seeds, vec2<i32>(in.position.xy), 0).xy;
+    if (seed.x < 0.0) {
+        discard;
+    }
+    let seed_pixel = vec2<i32>(seed);
+    let glow = textureLoad(params, seed_pixel, 0).xy;
+    let seed_distance = distance(in.position.xy, seed);
+    // The glowing mesh itself is left as it is
+    if (seed_distance < 0.5 || seed_distance > glow.x) {
+        discard;
+    }
+    // 0 at the silhouette and 1 at the outer edge of the glow
+    let t = pow(seed_distance / glow.x, glow.y);
+    return mix(textureLoad(inner_colors, seed_pixel, 0), textureLoad(outer_colors, seed_pixel, 0), t);
+}
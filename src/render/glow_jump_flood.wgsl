@@ -0,0 +1,37 @@
+#import bevy_core_pipeline::fullscreen_vertex_shader
+
+struct JumpFloodStep {
+    step: i32,
+};
+
+// Pixel coordinates of the nearest glowing pixel found so far, negative where there is none
+@group(0) @binding(0)
+var seeds: texture_2d<f32>;
+@group(0) @binding(1)
+var<uniform> jump_flood: JumpFloodStep;
+
+@fragment
+fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    let size = vec2<i32>(textureDimensions(seeds));
+    let pixel = vec2<i32>(in.position.xy);
+    var nearest = vec2<f32>(-1.0, -1.0);
+    var nearest_distance = 3.4e38;
+    for (var y: i32 = -1; y <= 1; y = y + 1) {
+        for (var x: i32 = -1; x <= 1; x = x + 1) {
+            let sample = pixel + vec2<i32>(x, y) * jump_flood.step;
+            if (any(sample < vec2<i32>(0, 0)) || any(sample >= size)) {
+                continue;
+            }
+            let seed = textureLoad(seeds, sample, 0).xy;
+            if (seed.x < 0.0) {
+                continue;
+            }
+            let seed_distance = distance(in.position.xy, seed);
+            if (seed_distance < nearest_distance) {
+                nearest = seed;
+                nearest_distance = seed_distance;
+            }
+        }
+    }
+    return vec4<f32>(nearest, 0.0, 0.0);
+}
@@ -1,45 +1,126 @@
 #import bevy_pbr::mesh_types
 #import bevy_pbr::mesh_view_bindings
+#import bevy_outline::outline_functions
 
 struct Vertex {
     @location(0) position: vec3<f32>,
     @location(1) normal: vec3<f32>,
+#ifdef VERTEX_OUTLINE_WIDTH
+    @location(2) width: f32,
+#endif
+#ifdef INSTANCED
+    // Columns of the model matrix of the instance
+    @location(3) model_x: vec4<f32>,
+    @location(4) model_y: vec4<f32>,
+    @location(5) model_z: vec4<f32>,
+    @location(6) model_w: vec4<f32>,
+#endif
 };
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
+    // Screen position of the mesh origin in pixels
+    @location(0) @interpolate(flat) center: vec2<f32>,
+    // Distance in pixels from the mesh origin to the unextruded vertex on screen
+    @location(1) inner_radius: f32,
+    // Width of the outline band in pixels
+    @location(2) extrusion: f32,
 };
 
-@group(1) @binding(0)
-var<uniform> mesh: Mesh;
+// Color targets of the glow mask, see `glow.rs`
+struct GlowMask {
+    // Pixel coordinates of the glowing fragment
+    @location(0) seed: vec2<f32>,
+    @location(1) inner_color: vec4<f32>,
+    @location(2) outer_color: vec4<f32>,
+    // Width of the glow in pixels and its falloff
+    @location(3) params: vec2<f32>,
+};
 
 struct OutlineMat {
     width: f32,
     color: vec4<f32>,
+    dash_length: f32,
+    dash_gap: f32,
+    dash_speed: f32,
+    flags: u32,
+    gap_color: vec4<f32>,
+    glow_color: vec4<f32>,
+    glow_falloff: f32,
 };
 
+let OUTLINE_FLAGS_DASHED_BIT: u32 = 1u;
+let OUTLINE_FLAGS_GAP_COLOR_BIT: u32 = 2u;
+let OUTLINE_FLAGS_LOGICAL_PIXELS_BIT: u32 = 8u;
+let TAU: f32 = 6.283185307179586;
+
 @group(2) @binding(0)
 var<uniform> outline_mat: OutlineMat;
 
-struct DoubleReciprocalWindowSize {
-    size: vec2<f32>,
-};
-
-@group(3) @binding(0)
-var<uniform> window_size: DoubleReciprocalWindowSize;
+// Physical pixels per unit of the outline width
+fn pixel_scale() -> f32 {
+    if ((outline_mat.flags & OUTLINE_FLAGS_LOGICAL_PIXELS_BIT) != 0u) {
+        return window_size.scale_factor;
+    }
+    return 1.0;
+}
 
 @vertex
 fn vertex(vertex: Vertex) -> VertexOutput {
-    let mvp = view.view_proj * mesh.model;
-    let clip_position = mvp * vec4<f32>(vertex.position, 1.0);
-    let clip_normal = mvp * vec4<f32>(vertex.normal, 0.0);
-    let extrude_offset = normalize(clip_normal.xy) * outline_mat.width * clip_position.w * window_size.size;
+    var width = outline_mat.width * pixel_scale();
+#ifdef VERTEX_OUTLINE_WIDTH
+    width = width * vertex.width;
+#endif
+    var model = mesh.model;
+#ifdef INSTANCED
+    model = mat4x4<f32>(vertex.model_x, vertex.model_y, vertex.model_z, vertex.model_w);
+#endif
     var out: VertexOutput;
-    out.clip_position = vec4<f32>(clip_position.xy + extrude_offset, clip_position.zw);
+    let clip_inner = view.view_proj * model * vec4<f32>(vertex.position, 1.0);
+#ifdef OUTLINE_GLOW_MASK
+    // The mask of a glow is the unextruded mesh
+    out.clip_position = clip_inner;
+#else
+    out.clip_position = outline_clip_position_model(model, vertex.position, vertex.normal, width);
+#endif
+    let clip_center = view.view_proj * model * vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    let ndc_center = clip_center.xy / clip_center.w;
+    out.center = view.viewport.xy + (vec2<f32>(ndc_center.x, -ndc_center.y) * 0.5 + 0.5) * view.viewport.zw;
+    let ndc_inner = clip_inner.xy / clip_inner.w;
+    let inner = view.viewport.xy + (vec2<f32>(ndc_inner.x, -ndc_inner.y) * 0.5 + 0.5) * view.viewport.zw;
+    out.inner_radius = length(inner - out.center);
+    out.extrusion = min(width * window_size.width_scale, window_size.max_width);
     return out;
 }
 
 @fragment
-fn fragment() -> @location(0) vec4<f32> {
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+    if ((outline_mat.flags & OUTLINE_FLAGS_DASHED_BIT) != 0u) {
+        // Approximate the distance along the silhouette by the arc length around the mesh origin,
+        // measured at the unextruded edge so that dashes do not stretch across the outline width
+        let offset = in.clip_position.xy - in.center;
+        let radius = max(in.inner_radius / pixel_scale(), 1.0);
+        let period = outline_mat.dash_length + outline_mat.dash_gap;
+        // A whole number of periods around the mesh, so that they line up where the angle wraps
+        let periods = max(round(TAU * radius / period), 1.0);
+        let turn = atan2(offset.y, offset.x) / TAU + 0.5;
+        let phase = fract(turn * periods + globals.time * outline_mat.dash_speed / period);
+        if (phase * period >= outline_mat.dash_length) {
+            if ((outline_mat.flags & OUTLINE_FLAGS_GAP_COLOR_BIT) != 0u) {
+                return outline_mat.gap_color;
+            }
+            discard;
+        }
+    }
     return outline_mat.color;
 }
+
+@fragment
+fn glow_mask(in: VertexOutput) -> GlowMask {
+    var out: GlowMask;
+    out.seed = in.clip_position.xy;
+    out.inner_color = outline_mat.color;
+    out.outer_color = outline_mat.glow_color;
+    out.params = vec2<f32>(in.extrusion, outline_mat.glow_falloff);
+    return out;
+}
@@ -0,0 +1,55 @@
+#import bevy_pbr::mesh_types
+#import bevy_pbr::mesh_view_bindings
+#import bevy_outline::outline_functions
+
+// Default shaders of `OutlineMaterialTrait`. The uniform at binding 0 of the material
+// must start with the fields of `OutlineMat`.
+
+struct Vertex {
+    @location(0) position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+#ifdef VERTEX_OUTLINE_WIDTH
+    @location(2) width: f32,
+#endif
+#ifdef INSTANCED
+    // Columns of the model matrix of the instance
+    @location(3) model_x: vec4<f32>,
+    @location(4) model_y: vec4<f32>,
+    @location(5) model_z: vec4<f32>,
+    @location(6) model_w: vec4<f32>,
+#endif
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+struct OutlineMat {
+    // Width in physical pixels
+    width: f32,
+    color: vec4<f32>,
+};
+
+@group(2) @binding(0)
+var<uniform> outline_mat: OutlineMat;
+
+@vertex
+fn vertex(vertex: Vertex) -> VertexOutput {
+    var width = outline_mat.width;
+#ifdef VERTEX_OUTLINE_WIDTH
+    width = width * vertex.width;
+#endif
+    var model = mesh.model;
+#ifdef INSTANCED
+    model = mat4x4<f32>(vertex.model_x, vertex.model_y, vertex.model_z, vertex.model_w);
+#endif
+    var out: VertexOutput;
+    out.clip_position = outline_clip_position_model(model, vertex.position, vertex.normal, width);
+    return out;
+}
+
+// Reads no vertex outputs, so that it can follow any vertex shader
+@fragment
+fn fragment() -> @location(0) vec4<f32> {
+    return outline_mat.color;
+}
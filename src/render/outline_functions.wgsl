@@ -0,0 +1,33 @@
+#define_import_path bevy_outline::outline_functions
+
+// Importers must import `bevy_pbr::mesh_types` and `bevy_pbr::mesh_view_bindings` beforehand.
+
+@group(1) @binding(0)
+var<uniform> mesh: Mesh;
+
+struct DoubleReciprocalWindowSize {
+    size: vec2<f32>,
+    // Factor multiplied into all outline widths
+    width_scale: f32,
+    // Upper bound of outline widths in pixels
+    max_width: f32,
+    // Physical pixels per logical pixel
+    scale_factor: f32,
+};
+
+@group(3) @binding(0)
+var<uniform> window_size: DoubleReciprocalWindowSize;
+
+// Extrude the vertex along the outline normal by `width` pixels in clip space.
+fn outline_clip_position(position: vec3<f32>, normal: vec3<f32>, width: f32) -> vec4<f32> {
+    return outline_clip_position_model(mesh.model, position, normal, width);
+}
+
+// Same as `outline_clip_position` with an explicit model matrix, e.g. of an instance.
+fn outline_clip_position_model(model: mat4x4<f32>, position: vec3<f32>, normal: vec3<f32>, width: f32) -> vec4<f32> {
+    let mvp = view.view_proj * model;
+    let clip_position = mvp * vec4<f32>(position, 1.0);
+    let clip_normal = mvp * vec4<f32>(normal, 0.0);
+    let extrude_offset = normalize(clip_normal.xy) * min(width * window_size.width_scale, window_size.max_width) * clip_position.w * window_size.size;
+    return vec4<f32>(clip_position.xy + extrude_offset, clip_position.zw);
+}
@@ -0,0 +1,351 @@
+//! Box and lasso multi-selection, e.g. for RTS units.
+
+use bevy::{
+    input::InputSystem,
+    math::{Vec2, Vec3},
+    prelude::{
+        App, Camera, Commands, Component, CoreStage, Entity, EventReader, EventWriter,
+        GlobalTransform, Input, IntoSystemDescriptor, KeyCode, MouseButton, Plugin, Query, Res,
+        ResMut, Resource, SystemLabel, With,
+    },
+    render::primitives::Aabb,
+    utils::HashSet,
+    window::Windows,
+};
+
+use crate::highlight::{active_camera, add_highlighting, PickingSelected};
+
+/// Selects [`Selectable`] entities inside a rectangle or lasso on screen by inserting the
+/// [`PickingSelected`] marker, so they show the [`SelectedOutline`](crate::highlight::SelectedOutline).
+///
+/// Selections are requested with [`SelectRegion`] events. Unless `mouse_box` is disabled,
+/// dragging with the left mouse button selects with a box, holding shift adds to the selection.
+pub struct OutlineSelectionPlugin {
+    pub mouse_box: bool,
+}
+
+impl Default for OutlineSelectionPlugin {
+    fn default() -> Self {
+        Self { mouse_box: true }
+    }
+}
+
+impl Plugin for OutlineSelectionPlugin {
+    fn build(&self, app: &mut App) {
+        add_highlighting(app);
+        app.init_resource::<SelectionDrag>()
+            .add_event::<SelectRegion>()
+            .add_event::<SelectionChanged>()
+            .add_system_to_stage(
+                CoreStage::PreUpdate,
+                select_region
+                    .label(SelectionSystem::Select)
+                    .after(SelectionSystem::Input),
+            );
+        if self.mouse_box {
+            app.add_system_to_stage(
+                CoreStage::PreUpdate,
+                mouse_box.label(SelectionSystem::Input).after(InputSystem),
+            );
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, SystemLabel)]
+pub enum SelectionSystem {
+    /// Sends [`SelectRegion`] events from mouse drags
+    Input,
+    /// Updates the selection
+    Select,
+}
+
+/// Marks entities which can be selected with a box or lasso
+#[derive(Component, Default)]
+pub struct Selectable;
+
+/// Region on screen, in logical pixels from the bottom-left corner of the viewport.
+///
+/// Both shapes select the entities whose projected AABB center is inside them.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SelectionShape {
+    /// Axis-aligned rectangle
+    Rect { min: Vec2, max: Vec2 },
+    /// Polygon through the points, closed from the last point back to the first
+    Lasso(Vec<Vec2>),
+}
+
+impl SelectionShape {
+    /// Rectangle spanned by two corners in any order
+    pub fn rect(a: Vec2, b: Vec2) -> Self {
+        SelectionShape::Rect {
+            min: a.min(b),
+            max: a.max(b),
+        }
+    }
+
+    fn contains(&self, point: Vec2) -> bool {
+        match self {
+            SelectionShape::Rect { min, max } => point.cmpge(*min).all() && point.cmple(*max).all(),
+            SelectionShape::Lasso(points) => polygon_contains(points, point),
+        }
+    }
+}
+
+/// How a region changes the current selection
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SelectionMode {
+    /// Select only the entities in the region
+    #[default]
+    Replace,
+    /// Add the entities in the region to the selection
+    Add,
+    /// Remove the entities in the region from the selection
+    Remove,
+}
+
+/// Request to select the [`Selectable`] entities in a region
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelectRegion {
+    pub shape: SelectionShape,
+    pub mode: SelectionMode,
+}
+
+/// Sent when entities are selected or deselected by [`SelectRegion`]s
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SelectionChanged {
+    pub selected: Vec<Entity>,
+    pub deselected: Vec<Entity>,
+}
+
+/// Mouse drag in progress, e.g. to draw the selection box
+#[derive(Resource, Debug, Default)]
+pub struct SelectionDrag {
+    /// Corner where the drag started, and the current cursor position
+    pub corners: Option<(Vec2, Vec2)>,
+}
+
+/// Drags shorter than this, in logical pixels, are clicks and select nothing
+const MIN_DRAG: f32 = 4.;
+
+fn mouse_box(
+    windows: Option<Res<Windows>>,
+    mouse: Option<Res<Input<MouseButton>>>,
+    keys: Option<Res<Input<KeyCode>>>,
+    mut drag: ResMut<SelectionDrag>,
+    mut regions: EventWriter<SelectRegion>,
+) {
+    let (windows, mouse) = match (windows, mouse) {
+        (Some(windows), Some(mouse)) => (windows, mouse),
+        _ => return,
+    };
+    let cursor = windows
+        .get_primary()
+        .and_then(|window| window.cursor_position());
+
+    if mouse.just_pressed(MouseButton::Left) {
+        drag.corners = cursor.map(|cursor| (cursor, cursor));
+    } else if let Some((_, end)) = &mut drag.corners {
+        if let Some(cursor) = cursor {
+            *end = cursor;
+        }
+    }
+    if !mouse.just_released(MouseButton::Left) {
+        return;
+    }
+    if let Some((start, end)) = drag.corners.take() {
+        if start.distance(end) < MIN_DRAG {
+            return;
+        }
+        let add = keys.map_or(false, |keys| {
+            keys.any_pressed([KeyCode::LShift, KeyCode::RShift])
+        });
+        regions.send(SelectRegion {
+            shape: SelectionShape::rect(start, end),
+            mode: if add {
+                SelectionMode::Add
+            } else {
+                SelectionMode::Replace
+            },
+        });
+    }
+}
+
+fn select_region(
+    mut commands: Commands,
+    mut requests: EventReader<SelectRegion>,
+    mut changes: EventWriter<SelectionChanged>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    selectables: Query<
+        (
+            Entity,
+            &GlobalTransform,
+            Option<&Aabb>,
+            Option<&PickingSelected>,
+        ),
+        With<Selectable>,
+    >,
+) {
+    let regions: Vec<&SelectRegion> = requests.iter().collect();
+    if regions.is_empty() {
+        return;
+    }
+    let (camera, camera_transform) = match active_camera(cameras.iter()) {
+        Some(camera) => camera,
+        None => return,
+    };
+
+    // Screen bounds centers of the entities in front of the camera
+    let centers: Vec<(Entity, Vec2)> = selectables
+        .iter()
+        .filter_map(|(entity, transform, aabb, _)| {
+            let corners: Vec<Vec3> = match aabb {
+                Some(aabb) => {
+                    let model = transform.compute_matrix();
+                    let (center, half) = (Vec3::from(aabb.center), Vec3::from(aabb.half_extents));
+                    (0..8)
+                        .map(|i| {
+                            let sign = Vec3::new(
+                                if i & 1 == 0 { -1. } else { 1. },
+                                if i & 2 == 0 { -1. } else { 1. },
+                                if i & 4 == 0 { -1. } else { 1. },
+                            );
+                            model.transform_point3(center + sign * half)
+                        })
+                        .collect()
+                }
+                None => vec![transform.translation()],
+            };
+            let projected: Vec<Vec2> = corners
+                .into_iter()
+                .filter_map(|corner| camera.world_to_viewport(camera_transform, corner))
+                .collect();
+            let min = projected.iter().copied().reduce(Vec2::min)?;
+            let max = projected.iter().copied().reduce(Vec2::max)?;
+            Some((entity, (min + max) / 2.))
+        })
+        .collect();
+
+    let previous: HashSet<Entity> = selectables
+        .iter()
+        .filter_map(|(entity, .., selected)| selected.map(|_| entity))
+        .collect();
+    let mut selection = previous.clone();
+    for region in regions {
+        let inside = centers
+            .iter()
+            .filter(|(_, center)| region.shape.contains(*center))
+            .map(|(entity, _)| *entity);
+        apply_region(&mut selection, region.mode, inside);
+    }
+
+    let change = SelectionChanged {
+        selected: selection.difference(&previous).copied().collect(),
+        deselected: previous.difference(&selection).copied().collect(),
+    };
+    if change.selected.is_empty() && change.deselected.is_empty() {
+        return;
+    }
+    for entity in &change.selected {
+        commands.entity(*entity).insert(PickingSelected);
+    }
+    for entity in &change.deselected {
+        commands.entity(*entity).remove::<PickingSelected>();
+    }
+    changes.send(change);
+}
+
+/// Change `selection` by the entities inside a region
+fn apply_region(
+    selection: &mut HashSet<Entity>,
+    mode: SelectionMode,
+    inside: impl Iterator<Item = Entity>,
+) {
+    match mode {
+        SelectionMode::Replace => *selection = inside.collect(),
+        SelectionMode::Add => selection.extend(inside),
+        SelectionMode::Remove => {
+            for entity in inside {
+                selection.remove(&entity);
+            }
+        }
+    }
+}
+
+/// Even-odd test of a point against a polygon
+fn polygon_contains(points: &[Vec2], point: Vec2) -> bool {
+    let mut inside = false;
+    for (i, a) in points.iter().enumerate() {
+        let b = points[(i + 1) % points.len()];
+        if (a.y > point.y) != (b.y > point.y)
+            && point.x < a.x + (point.y - a.y) / (b.y - a.y) * (b.x - a.x)
+        {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entities(ids: &[u32]) -> HashSet<Entity> {
+        ids.iter().copied().map(Entity::from_raw).collect()
+    }
+
+    #[test]
+    fn polygon() {
+        // Concave "L" shape
+        let points = [
+            Vec2::new(0., 0.),
+            Vec2::new(2., 0.),
+            Vec2::new(2., 1.),
+            Vec2::new(1., 1.),
+            Vec2::new(1., 2.),
+            Vec2::new(0., 2.),
+        ];
+        assert!(polygon_contains(&points, Vec2::new(0.5, 0.5)));
+        assert!(polygon_contains(&points, Vec2::new(1.5, 0.5)));
+        assert!(polygon_contains(&points, Vec2::new(0.5, 1.5)));
+        // Inside the notch of the "L"
+        assert!(!polygon_contains(&points, Vec2::new(1.5, 1.5)));
+        assert!(!polygon_contains(&points, Vec2::new(-0.5, 0.5)));
+        assert!(!polygon_contains(&[], Vec2::ZERO));
+    }
+
+    #[test]
+    fn shapes_select_by_center() {
+        let rect = SelectionShape::rect(Vec2::new(2., 2.), Vec2::ZERO);
+        assert!(rect.contains(Vec2::new(1., 1.)));
+        assert!(!rect.contains(Vec2::new(3., 1.)));
+
+        let lasso = SelectionShape::Lasso(vec![Vec2::ZERO, Vec2::new(2., 0.), Vec2::new(0., 2.)]);
+        assert!(lasso.contains(Vec2::new(0.5, 0.5)));
+        assert!(!lasso.contains(Vec2::new(1.5, 1.5)));
+    }
+
+    #[test]
+    fn region_modes() {
+        let mut selection = entities(&[1, 2]);
+        apply_region(
+            &mut selection,
+            SelectionMode::Add,
+            entities(&[2, 3]).into_iter(),
+        );
+        assert_eq!(selection, entities(&[1, 2, 3]));
+
+        apply_region(
+            &mut selection,
+            SelectionMode::Remove,
+            entities(&[1, 4]).into_iter(),
+        );
+        assert_eq!(selection, entities(&[2, 3]));
+
+        apply_region(
+            &mut selection,
+            SelectionMode::Replace,
+            entities(&[4]).into_iter(),
+        );
+        assert_eq!(selection, entities(&[4]));
+    }
+}
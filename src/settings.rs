@@ -0,0 +1,34 @@
+use bevy::{
+    prelude::{Reflect, ReflectResource, Resource},
+    render::extract_resource::ExtractResource,
+};
+
+/// Runtime settings of all outlines, e.g. for a settings menu.
+///
+/// Changes take effect in the next frame without re-creating any outline asset.
+#[derive(Resource, Clone, Debug, ExtractResource, Reflect)]
+#[reflect(Resource)]
+pub struct OutlineSettings {
+    /// Draw outlines at all
+    pub enabled: bool,
+    /// Factor multiplied into the width of every outline, e.g. for accessibility
+    pub width_scale: f32,
+    /// Maximum number of outlines drawn per view and outline material type.
+    /// The outlines nearest to the camera are kept.
+    pub max_outlines: Option<usize>,
+    /// Pack the uniforms of outline materials into one buffer per material type instead of
+    /// a buffer and bind group per material, see `OutlineMaterialTrait::shared_uniform`.
+    /// Applies to materials prepared after the change.
+    pub shared_material_uniforms: bool,
+}
+
+impl Default for OutlineSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            width_scale: 1.,
+            max_outlines: None,
+            shared_material_uniforms: true,
+        }
+    }
+}
@@ -10,7 +10,7 @@ use bevy::{
 /// An ordered float3 array which implements Eq and Hash
 #[derive(Debug, Clone, Copy, Deref, DerefMut, Default)]
 #[repr(transparent)]
-struct Float3Ord([f32; 3]);
+pub(crate) struct Float3Ord(pub [f32; 3]);
 
 impl PartialEq for Float3Ord {
     fn eq(&self, other: &Self) -> bool {
@@ -55,7 +55,7 @@ pub(crate) fn smooth_normal(mesh: &Mesh) -> VertexAttributeValues {
 }
 
 #[inline(always)]
-fn get_float3x3(values: &VertexAttributeValues) -> &Vec<[f32; 3]> {
+pub(crate) fn get_float3x3(values: &VertexAttributeValues) -> &Vec<[f32; 3]> {
     match values {
         VertexAttributeValues::Float32x3(v) => v,
         _ => panic!("Vertex Position must be a Float32x3"),
@@ -0,0 +1,143 @@
+use std::{marker::PhantomData, num::NonZeroU64, ops::Range};
+
+use bevy::{
+    prelude::{FromWorld, Resource, World},
+    render::{
+        render_resource::{
+            encase::{self, internal::WriteInto},
+            BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindingResource,
+            Buffer, BufferBinding, BufferDescriptor, BufferUsages, ShaderType,
+        },
+        renderer::{RenderDevice, RenderQueue},
+    },
+};
+
+use crate::OutlineMaterialTrait;
+
+/// Uniform of a material packed into the [`OutlineUniformBuffer`] of its type,
+/// see [`OutlineMaterialTrait::shared_uniform`].
+pub struct SharedOutlineUniform<M: OutlineMaterialTrait> {
+    pub(crate) key: M::Data,
+    pub(crate) bytes: Vec<u8>,
+}
+
+impl<M: OutlineMaterialTrait> SharedOutlineUniform<M> {
+    /// The pipeline key of the material and its uniform, which must be
+    /// [`OutlineMaterialTrait::shared_uniform_size`] bytes large.
+    pub fn new<U: ShaderType + WriteInto>(key: M::Data, uniform: &U) -> Self {
+        let mut buffer = encase::UniformBuffer::new(Vec::new());
+        buffer.write(uniform).unwrap();
+        Self {
+            key,
+            bytes: buffer.into_inner(),
+        }
+    }
+}
+
+/// Uniforms of all materials of a type which share one buffer, each at its own dynamic offset.
+///
+/// Modified materials are written into their slot, and the modified range of the GPU buffer is
+/// written once per frame. The buffer and its bind group are only recreated when it has to grow.
+#[derive(Resource)]
+pub struct OutlineUniformBuffer<M: OutlineMaterialTrait> {
+    data: Vec<u8>,
+    /// Offsets of the slots whose material was removed
+    free: Vec<u32>,
+    /// Distance between two slots, the uniform size rounded up to the dynamic offset alignment
+    stride: u32,
+    modified: Option<Range<usize>>,
+    buffer: Option<Buffer>,
+    bind_group: Option<BindGroup>,
+    marker: PhantomData<M>,
+}
+
+impl<M: OutlineMaterialTrait> FromWorld for OutlineUniformBuffer<M> {
+    fn from_world(world: &mut World) -> Self {
+        let alignment = world
+            .resource::<RenderDevice>()
+            .limits()
+            .min_uniform_buffer_offset_alignment;
+        let size = M::shared_uniform_size().map_or(0, NonZeroU64::get) as u32;
+        Self {
+            data: Vec::new(),
+            free: Vec::new(),
+            stride: (size + alignment - 1) / alignment * alignment,
+            modified: None,
+            buffer: None,
+            bind_group: None,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<M: OutlineMaterialTrait> OutlineUniformBuffer<M> {
+    /// Reserves the slot of a material, returns its dynamic offset
+    pub(crate) fn allocate(&mut self) -> u32 {
+        self.free.pop().unwrap_or_else(|| {
+            let offset = self.data.len();
+            self.data.resize(offset + self.stride as usize, 0);
+            offset as u32
+        })
+    }
+
+    pub(crate) fn free(&mut self, offset: u32) {
+        self.free.push(offset);
+    }
+
+    pub(crate) fn write(&mut self, offset: u32, uniform: &SharedOutlineUniform<M>) {
+        let range = offset as usize..offset as usize + uniform.bytes.len();
+        self.data[range.clone()].copy_from_slice(&uniform.bytes);
+        self.modified = Some(match self.modified.take() {
+            Some(modified) => modified.start.min(range.start)..modified.end.max(range.end),
+            None => range,
+        });
+    }
+
+    pub(crate) fn bind_group(&self) -> Option<&BindGroup> {
+        self.bind_group.as_ref()
+    }
+
+    /// Writes the slots modified since the last call
+    pub(crate) fn write_buffer(
+        &mut self,
+        render_device: &RenderDevice,
+        render_queue: &RenderQueue,
+        layout: Option<&BindGroupLayout>,
+    ) {
+        let (modified, layout, size) =
+            match (self.modified.take(), layout, M::shared_uniform_size()) {
+                (Some(modified), Some(layout), Some(size)) => (modified, layout, size),
+                _ => return,
+            };
+
+        let capacity = self.buffer.as_ref().map_or(0, |buffer| buffer.size());
+        if capacity < self.data.len() as u64 {
+            let buffer = render_device.create_buffer(&BufferDescriptor {
+                label: Some("outline uniform buffer"),
+                size: (self.data.len() as u64).next_power_of_two(),
+                usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+            self.bind_group = Some(render_device.create_bind_group(&BindGroupDescriptor {
+                label: Some("outline uniform bind group"),
+                layout,
+                entries: &[BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::Buffer(BufferBinding {
+                        buffer: &buffer,
+                        offset: 0,
+                        size: Some(size),
+                    }),
+                }],
+            }));
+            self.buffer = Some(buffer);
+            // The new buffer holds nothing yet
+            render_queue.write_buffer(self.buffer.as_ref().unwrap(), 0, &self.data);
+            return;
+        }
+
+        if let Some(buffer) = &self.buffer {
+            render_queue.write_buffer(buffer, modified.start as u64, &self.data[modified]);
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use bevy::{
+    math::Vec3,
+    render::mesh::{Mesh, PrimitiveTopology, VertexAttributeValues},
+    utils::HashMap,
+};
+use wgpu_types::VertexFormat;
+
+use crate::{
+    smooth_normal::{get_float3x3, smooth_normal, Float3Ord},
+    ATTRIBUTE_OUTLINE_NORMAL,
+};
+
+/// Channel of the vertex color used by [`outline_width_from_vertex_colors`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VertexColorChannel {
+    Red,
+    Green,
+    Blue,
+    #[default]
+    Alpha,
+}
+
+/// Derive the per-vertex outline width factor from one channel of `Mesh::ATTRIBUTE_COLOR`.
+///
+/// The result can be inserted as `ATTRIBUTE_OUTLINE_WIDTH`.
+///
+/// # Panics
+/// Panics if the mesh has no vertex colors.
+pub fn outline_width_from_vertex_colors(
+    mesh: &Mesh,
+    channel: VertexColorChannel,
+) -> VertexAttributeValues {
+    let colors = match mesh.attribute(Mesh::ATTRIBUTE_COLOR).unwrap() {
+        VertexAttributeValues::Float32x4(v) => v,
+        values => panic!(
+            "Vertex Color must be a Float32x4, got {:?}",
+            VertexFormat::from(values)
+        ),
+    };
+    let index = channel as usize;
+    VertexAttributeValues::Float32(colors.iter().map(|color| color[index]).collect())
+}
+
+/// Derive the per-vertex outline width factor from the curvature of the mesh.
+///
+/// Flat regions get a factor of `1.0`, the most curved regions (e.g. fingertips) get `min_factor`.
+/// Only `TriangleList` meshes with `Float32x3` positions and normals are analyzed,
+/// other meshes get a uniform factor of `1.0`.
+/// The result can be inserted as `ATTRIBUTE_OUTLINE_WIDTH`.
+pub fn outline_width_from_curvature(mesh: &Mesh, min_factor: f32) -> VertexAttributeValues {
+    let uniform = || VertexAttributeValues::Float32(vec![1.; mesh.count_vertices()]);
+    let v_positions = match mesh.attribute(Mesh::ATTRIBUTE_POSITION) {
+        Some(VertexAttributeValues::Float32x3(positions)) => positions,
+        _ => return uniform(),
+    };
+    if mesh.primitive_topology() != PrimitiveTopology::TriangleList {
+        return uniform();
+    }
+
+    let smoothed_normals;
+    let v_normals = match (
+        mesh.attribute(ATTRIBUTE_OUTLINE_NORMAL),
+        mesh.attribute(Mesh::ATTRIBUTE_NORMAL),
+    ) {
+        (Some(VertexAttributeValues::Float32x3(normals)), _) => normals,
+        (None, Some(VertexAttributeValues::Float32x3(_))) => {
+            smoothed_normals = smooth_normal(mesh);
+            get_float3x3(&smoothed_normals)
+        }
+        _ => return uniform(),
+    };
+    if v_normals.len() != v_positions.len() {
+        return uniform();
+    }
+
+    let triangles: Vec<usize> = match mesh.indices() {
+        Some(indices) => indices.iter().collect(),
+        None => (0..v_positions.len()).collect(),
+    };
+
+    // Accumulate curvature on welded positions so that seams share the same value
+    let mut curvature_map: HashMap<Float3Ord, (f32, u32)> = HashMap::new();
+    for triangle in triangles.chunks_exact(3) {
+        for (a, b) in [
+            (triangle[0], triangle[1]),
+            (triangle[1], triangle[2]),
+            (triangle[2], triangle[0]),
+        ] {
+            let distance = Vec3::from(v_positions[a]).distance(Vec3::from(v_positions[b]));
+            if distance <= f32::EPSILON {
+                continue;
+            }
+            let bending = 1. - Vec3::from(v_normals[a]).dot(Vec3::from(v_normals[b]));
+            let curvature = bending.max(0.) / distance;
+            for index in [a, b] {
+                let entry = curvature_map
+                    .entry(Float3Ord(v_positions[index]))
+                    .or_insert((0., 0));
+                entry.0 += curvature;
+                entry.1 += 1;
+            }
+        }
+    }
+
+    let v_curvatures: Vec<f32> = v_positions
+        .iter()
+        .map(|pos| match curvature_map.get(&Float3Ord(*pos)) {
+            Some((sum, count)) => sum / *count as f32,
+            None => 0.,
+        })
+        .collect();
+    let max_curvature = v_curvatures.iter().copied().fold(0., f32::max);
+    if max_curvature <= f32::EPSILON {
+        return uniform();
+    }
+
+    VertexAttributeValues::Float32(
+        v_curvatures
+            .into_iter()
+            .map(|curvature| 1. - (1. - min_factor) * curvature / max_curvature)
+            .collect(),
+    )
+}
@@ -0,0 +1,36 @@
+use std::sync::Mutex;
+
+use bevy::{
+    log::warn,
+    prelude::{Entity, Query, ResMut, Resource},
+    render::Extract,
+    utils::HashSet,
+};
+
+/// Problems already reported by the outline render commands, so that each is logged only once
+/// per entity instead of every frame.
+///
+/// Extracted entities keep their main-world ids, so the reports of an entity are forgotten
+/// once it is despawned in the main world.
+#[derive(Resource, Default)]
+pub struct OutlineWarnings(Mutex<HashSet<(Entity, &'static str)>>);
+
+impl OutlineWarnings {
+    pub(crate) fn warn_once(&self, entity: Entity, message: &'static str) {
+        if self.0.lock().unwrap().insert((entity, message)) {
+            warn!("{} for entity {:?}, skipping its outline", message, entity);
+        }
+    }
+}
+
+/// Forget the reports of despawned entities, whose ids may be reused
+pub(crate) fn prune_outline_warnings(
+    mut warnings: ResMut<OutlineWarnings>,
+    entities: Extract<Query<Entity>>,
+) {
+    warnings
+        .0
+        .get_mut()
+        .unwrap()
+        .retain(|(entity, _)| entities.contains(*entity));
+}
@@ -1,96 +1,148 @@
 use bevy::{
-    core::cast_slice,
-    ecs::system::{lifetimeless::SRes, SystemParamItem},
+    ecs::system::{
+        lifetimeless::{Read, SQuery, SRes},
+        SystemParamItem,
+    },
     math::Vec2,
-    prelude::{Commands, Entity, EventReader, Res, ResMut, Resource},
+    prelude::{Camera, Commands, Component, Entity, Query, Res, ResMut, Resource},
     render::{
+        camera::RenderTarget,
         render_phase::{EntityRenderCommand, RenderCommandResult, TrackedRenderPass},
-        render_resource::{BindGroup, BindGroupDescriptor, BindGroupEntry, Buffer, ShaderType},
+        render_resource::{
+            BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, DynamicUniformBuffer,
+            ShaderType,
+        },
         renderer::{RenderDevice, RenderQueue},
+        view::ExtractedView,
         Extract,
     },
-    window::WindowResized,
+    window::Windows,
 };
 
-use crate::OutlinePipeline;
-
-#[derive(Resource)]
-pub(crate) struct ExtractedWindowSize {
-    width: f32,
-    height: f32,
-}
+use crate::{warnings::OutlineWarnings, OutlinePlugin, OutlineSettings};
 
 #[derive(ShaderType)]
 pub(crate) struct DoubleReciprocalWindowSizeUniform {
     size: Vec2,
+    width_scale: f32,
+    max_width: f32,
+    scale_factor: f32,
 }
 
 #[derive(Resource)]
 pub(crate) struct DoubleReciprocalWindowSizeMeta {
-    pub buffer: Buffer,
+    pub buffer: DynamicUniformBuffer<DoubleReciprocalWindowSizeUniform>,
+    pub layout: BindGroupLayout,
     pub bind_group: Option<BindGroup>,
 }
 
-pub(crate) fn extract_window_size(
+/// Scale factor of the render target of a view
+#[derive(Component)]
+pub(crate) struct ExtractedScaleFactor(f32);
+
+pub(crate) fn extract_scale_factors(
     mut commands: Commands,
-    mut resized_events: Extract<EventReader<WindowResized>>,
+    cameras: Extract<Query<(Entity, &Camera)>>,
+    windows: Extract<Res<Windows>>,
 ) {
-    if let Some(size_change) = resized_events.iter().last() {
-        if size_change.id.is_primary() {
-            let width = size_change.width;
-            let height = size_change.height;
-            commands.insert_resource(ExtractedWindowSize { width, height });
-        }
+    for (entity, camera) in cameras.iter() {
+        let scale_factor = match &camera.target {
+            RenderTarget::Window(id) => windows
+                .get(*id)
+                .map_or(1., |window| window.scale_factor() as f32),
+            RenderTarget::Image(_) => 1.,
+        };
+        commands
+            .get_or_spawn(entity)
+            .insert(ExtractedScaleFactor(scale_factor));
     }
 }
 
+/// Dynamic offset of the window size uniform of a view
+#[derive(Component)]
+pub(crate) struct WindowSizeUniformOffset(u32);
+
+/// Write the viewport size of every view, so that outline widths are in physical pixels
+/// and no resize event is needed at startup.
 pub(crate) fn prepare_window_size(
-    window_size: Res<ExtractedWindowSize>,
-    window_size_meta: ResMut<DoubleReciprocalWindowSizeMeta>,
+    mut commands: Commands,
+    views: Query<(Entity, &ExtractedView, Option<&ExtractedScaleFactor>)>,
+    mut window_size_meta: ResMut<DoubleReciprocalWindowSizeMeta>,
+    render_device: Res<RenderDevice>,
     render_queue: Res<RenderQueue>,
+    config: Res<OutlinePlugin>,
+    settings: Res<OutlineSettings>,
 ) {
-    if window_size.is_changed() {
-        let window_size_uniform = DoubleReciprocalWindowSizeUniform {
-            size: Vec2::new(2.0 / window_size.width, 2.0 / window_size.height),
-        };
-        render_queue.write_buffer(
-            &window_size_meta.buffer,
-            0,
-            cast_slice(&[window_size_uniform.size]),
-        )
+    window_size_meta.buffer.clear();
+    for (entity, view, scale_factor) in views.iter() {
+        // The viewport is in physical pixels and empty while a window is minimized
+        let size = Vec2::new(view.viewport.z as f32, view.viewport.w as f32).max(Vec2::ONE);
+        let offset = window_size_meta
+            .buffer
+            .push(DoubleReciprocalWindowSizeUniform {
+                size: 2.0 / size,
+                width_scale: settings.width_scale,
+                max_width: config.max_width,
+                scale_factor: scale_factor.map_or(1., |scale_factor| scale_factor.0),
+            });
+        commands
+            .entity(entity)
+            .insert(WindowSizeUniformOffset(offset));
     }
+    window_size_meta
+        .buffer
+        .write_buffer(&render_device, &render_queue);
 }
 
 pub(crate) fn queue_window_size_bind_group(
     render_device: Res<RenderDevice>,
     mut double_reciprocal_window_size_meta: ResMut<DoubleReciprocalWindowSizeMeta>,
-    pipeline: Res<OutlinePipeline>,
 ) {
-    let bind_group = render_device.create_bind_group(&BindGroupDescriptor {
-        label: Some("window size bind group"),
-        layout: &pipeline.window_size_layout,
-        entries: &[BindGroupEntry {
-            binding: 0,
-            resource: double_reciprocal_window_size_meta
-                .buffer
-                .as_entire_binding(),
-        }],
-    });
-    double_reciprocal_window_size_meta.bind_group = Some(bind_group);
+    let bind_group = double_reciprocal_window_size_meta
+        .buffer
+        .binding()
+        .map(|binding| {
+            render_device.create_bind_group(&BindGroupDescriptor {
+                label: Some("window size bind group"),
+                layout: &double_reciprocal_window_size_meta.layout,
+                entries: &[BindGroupEntry {
+                    binding: 0,
+                    resource: binding,
+                }],
+            })
+        });
+    double_reciprocal_window_size_meta.bind_group = bind_group;
 }
 
 pub(crate) struct SetWindowSizeBindGroup<const I: usize>;
 impl<const I: usize> EntityRenderCommand for SetWindowSizeBindGroup<I> {
-    type Param = SRes<DoubleReciprocalWindowSizeMeta>;
+    type Param = (
+        SRes<DoubleReciprocalWindowSizeMeta>,
+        SQuery<Read<WindowSizeUniformOffset>>,
+        SRes<OutlineWarnings>,
+    );
 
     fn render<'w>(
-        _view: Entity,
+        view: Entity,
         _item: Entity,
-        window_size: SystemParamItem<'w, '_, Self::Param>,
+        (window_size, view_query, warnings): SystemParamItem<'w, '_, Self::Param>,
         pass: &mut TrackedRenderPass<'w>,
     ) -> RenderCommandResult {
-        let window_size_bind_group = window_size.into_inner().bind_group.as_ref().unwrap();
-        pass.set_bind_group(I, window_size_bind_group, &[]);
+        let window_size_bind_group = match &window_size.into_inner().bind_group {
+            Some(bind_group) => bind_group,
+            None => {
+                warnings.warn_once(view, "Window size bind group is not ready");
+                return RenderCommandResult::Failure;
+            }
+        };
+        let offset = match view_query.get(view) {
+            Ok(offset) => offset,
+            Err(_) => {
+                warnings.warn_once(view, "Window size of the view is missing");
+                return RenderCommandResult::Failure;
+            }
+        };
+        pass.set_bind_group(I, window_size_bind_group, &[offset.0]);
 
         RenderCommandResult::Success
     }